@@ -0,0 +1,135 @@
+use serde::{Deserialize, Serialize};
+
+use crate::image::{GrayscaleColor, Image};
+
+/**
+ * How a layer's pixels are combined with the pixels already in an image. The 
+ * modes that come from image editors (screen, overlay, soft light) assume 
+ * values in 0..1.
+ */
+#[derive(Debug, Copy, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum BlendMode {
+    /// The layer replaces the image
+    Normal,
+    Add,
+    /// The layer is subtracted from the image
+    Subtract,
+    Multiply,
+    /// The inverse of multiplying the inverses; always brightens
+    Screen,
+    /// Multiply in the image's dark areas, screen in its bright ones; adds contrast
+    Overlay,
+    Min,
+    Max,
+    Difference,
+    /// A gentler overlay
+    SoftLight,
+}
+
+impl BlendMode {
+
+    pub fn blend(&self, base: GrayscaleColor, layer: GrayscaleColor) -> GrayscaleColor {
+        match self {
+            BlendMode::Normal => layer,
+            BlendMode::Add => base + layer,
+            BlendMode::Subtract => base - layer,
+            BlendMode::Multiply => base * layer,
+            BlendMode::Screen => 1.0 - (1.0 - base) * (1.0 - layer),
+            BlendMode::Overlay => {
+                if base < 0.5 {
+                    2.0 * base * layer
+                } else {
+                    1.0 - 2.0 * (1.0 - base) * (1.0 - layer)
+                }
+            },
+            BlendMode::Min => base.min(layer),
+            BlendMode::Max => base.max(layer),
+            BlendMode::Difference => (base - layer).abs(),
+            BlendMode::SoftLight => {
+                // W3C compositing spec version
+                if layer <= 0.5 {
+                    base - (1.0 - 2.0 * layer) * base * (1.0 - base)
+                } else {
+                    let d = if base <= 0.25 {
+                        ((16.0 * base - 12.0) * base + 4.0) * base
+                    } else {
+                        base.sqrt()
+                    };
+
+                    base + (2.0 * layer - 1.0) * (d - base)
+                }
+            },
+        }
+    }
+}
+
+/**
+ * Blends every pixel of layer into the same pixel of image. Both images must 
+ * have the same dimensions. Opacity fades between the original image (0) and 
+ * the fully blended result (1).
+ */
+pub fn blend_image(image: &mut Image<GrayscaleColor>, layer: &Image<GrayscaleColor>, blend_mode: BlendMode, opacity: f32) {
+    blend(image, layer, blend_mode, opacity, None);
+}
+
+/**
+ * Same as blend_image, but the opacity of each pixel is also multiplied by the
+ * mask's value there, so the layer only shows through where the mask is bright.
+ * The mask must be the same size as the image.
+ */
+pub fn blend_image_masked(image: &mut Image<GrayscaleColor>, layer: &Image<GrayscaleColor>, blend_mode: BlendMode, opacity: f32, mask: &Image<GrayscaleColor>) {
+    blend(image, layer, blend_mode, opacity, Some(mask));
+}
+
+fn blend(image: &mut Image<GrayscaleColor>, layer: &Image<GrayscaleColor>, blend_mode: BlendMode, opacity: f32, mask: Option<&Image<GrayscaleColor>>) {
+    assert!(image.width() == layer.width() && image.height() == layer.height(), "Layer must be the same size as the image");
+    if let Some(mask) = mask {
+        assert!(image.width() == mask.width() && image.height() == mask.height(), "Mask must be the same size as the image");
+    }
+
+    for x in 0..image.width() as i64 {
+        for y in 0..image.height() as i64 {
+            let base = *image.get(x, y);
+            let blended = blend_mode.blend(base, *layer.get(x, y));
+
+            let amount = match mask {
+                Some(mask) => opacity * mask.get(x, y).clamp(0.0, 1.0),
+                None => opacity,
+            };
+
+            image.set(x, y, base + (blended - base) * amount);
+        }
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::{blend_image_masked, BlendMode};
+    use crate::image::Image;
+
+    #[test]
+    fn test_modes() {
+        assert_eq!(BlendMode::Subtract.blend(0.75, 0.25), 0.5);
+        assert_eq!(BlendMode::Screen.blend(0.5, 0.5), 0.75);
+        assert_eq!(BlendMode::Overlay.blend(0.25, 0.5), 0.25);
+        assert_eq!(BlendMode::Overlay.blend(0.75, 0.5), 0.75);
+        assert_eq!(BlendMode::Difference.blend(0.25, 0.75), 0.5);
+        assert_eq!(BlendMode::SoftLight.blend(0.3, 0.5), 0.3);
+        assert!(BlendMode::SoftLight.blend(0.3, 0.8) > 0.3);
+    }
+
+    #[test]
+    fn test_opacity_and_mask() {
+        let mut image = Image::from_color(2, 1, 0.0);
+        let layer = Image::from_color(2, 1, 1.0);
+        let mut mask = Image::from_color(2, 1, 1.0);
+        mask.set(1, 0, 0.0);
+
+        blend_image_masked(&mut image, &layer, BlendMode::Normal, 0.5, &mask);
+
+        assert_eq!(*image.get(0, 0), 0.5);
+        assert_eq!(*image.get(1, 0), 0.0);
+    }
+}
@@ -0,0 +1,178 @@
+use std::ops::{Add, Mul, Sub};
+
+use crate::image::{GrayscaleColor, Image};
+
+/// An RGB color with each channel in 0..1
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Rgb {
+    pub r: f32,
+    pub g: f32,
+    pub b: f32,
+}
+
+/// An RGB color plus alpha, with each channel in 0..1
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Rgba {
+    pub r: f32,
+    pub g: f32,
+    pub b: f32,
+    pub a: f32,
+}
+
+impl Rgb {
+
+    pub fn new(r: f32, g: f32, b: f32) -> Self {
+        Rgb { r, g, b }
+    }
+
+    /// Perceived brightness (Rec. 709 weights)
+    pub fn luminance(&self) -> GrayscaleColor {
+        0.2126 * self.r + 0.7152 * self.g + 0.0722 * self.b
+    }
+}
+
+impl Rgba {
+
+    pub fn new(r: f32, g: f32, b: f32, a: f32) -> Self {
+        Rgba { r, g, b, a }
+    }
+
+    pub fn rgb(&self) -> Rgb {
+        Rgb { r: self.r, g: self.g, b: self.b }
+    }
+}
+
+// conversions
+impl From<GrayscaleColor> for Rgb {
+    fn from(value: GrayscaleColor) -> Self {
+        Rgb { r: value, g: value, b: value }
+    }
+}
+impl From<GrayscaleColor> for Rgba {
+    fn from(value: GrayscaleColor) -> Self {
+        Rgba { r: value, g: value, b: value, a: 1.0 }
+    }
+}
+impl From<Rgb> for Rgba {
+    fn from(color: Rgb) -> Self {
+        Rgba { r: color.r, g: color.g, b: color.b, a: 1.0 }
+    }
+}
+
+impl Image<GrayscaleColor> {
+
+    pub fn to_rgb(&self) -> Image<Rgb> {
+        self.map(Rgb::from)
+    }
+
+    pub fn to_rgba(&self) -> Image<Rgba> {
+        self.map(Rgba::from)
+    }
+}
+
+impl Image<Rgb> {
+
+    pub fn to_grayscale(&self) -> Image<GrayscaleColor> {
+        self.map(|color| color.luminance())
+    }
+
+    pub fn to_rgba(&self) -> Image<Rgba> {
+        self.map(Rgba::from)
+    }
+}
+
+impl Image<Rgba> {
+
+    /// Drops the alpha channel
+    pub fn to_rgb(&self) -> Image<Rgb> {
+        self.map(|color| color.rgb())
+    }
+}
+
+// standard traits
+impl Add for Rgb {
+    type Output = Rgb;
+
+    fn add(self, other: Self) -> Self::Output {
+        Rgb { r: self.r + other.r, g: self.g + other.g, b: self.b + other.b }
+    }
+}
+impl Sub for Rgb {
+    type Output = Rgb;
+
+    fn sub(self, other: Self) -> Self::Output {
+        Rgb { r: self.r - other.r, g: self.g - other.g, b: self.b - other.b }
+    }
+}
+impl Mul for Rgb {
+    type Output = Rgb;
+
+    // (component-wise)
+    fn mul(self, other: Self) -> Self::Output {
+        Rgb { r: self.r * other.r, g: self.g * other.g, b: self.b * other.b }
+    }
+}
+impl Mul<f32> for Rgb {
+    type Output = Rgb;
+
+    fn mul(self, scale: f32) -> Self::Output {
+        Rgb { r: self.r * scale, g: self.g * scale, b: self.b * scale }
+    }
+}
+
+impl Add for Rgba {
+    type Output = Rgba;
+
+    fn add(self, other: Self) -> Self::Output {
+        Rgba { r: self.r + other.r, g: self.g + other.g, b: self.b + other.b, a: self.a + other.a }
+    }
+}
+impl Sub for Rgba {
+    type Output = Rgba;
+
+    fn sub(self, other: Self) -> Self::Output {
+        Rgba { r: self.r - other.r, g: self.g - other.g, b: self.b - other.b, a: self.a - other.a }
+    }
+}
+impl Mul for Rgba {
+    type Output = Rgba;
+
+    // (component-wise)
+    fn mul(self, other: Self) -> Self::Output {
+        Rgba { r: self.r * other.r, g: self.g * other.g, b: self.b * other.b, a: self.a * other.a }
+    }
+}
+impl Mul<f32> for Rgba {
+    type Output = Rgba;
+
+    fn mul(self, scale: f32) -> Self::Output {
+        Rgba { r: self.r * scale, g: self.g * scale, b: self.b * scale, a: self.a * scale }
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::Rgb;
+    use crate::image::Image;
+
+    #[test]
+    fn test_image_ops() {
+        let mut image = Image::from_color(4, 4, Rgb::new(0.5, 0.25, 1.0));
+        image.add(1, 1, Rgb::new(0.25, 0.25, 0.0));
+        image.scale(1, 1, Rgb::new(2.0, 2.0, 0.5));
+
+        assert_eq!(*image.get(1, 1), Rgb::new(1.5, 1.0, 0.5));
+        assert_eq!(*image.get(0, 0), Rgb::new(0.5, 0.25, 1.0));
+    }
+
+    #[test]
+    fn test_conversions() {
+        let gray = Image::from_color(2, 2, 0.25);
+        let rgb = gray.to_rgb();
+
+        assert_eq!(*rgb.get(0, 0), Rgb::new(0.25, 0.25, 0.25));
+        assert!((rgb.to_grayscale().get(0, 0) - 0.25).abs() < 0.0001);
+        assert_eq!(rgb.to_rgba().get(0, 0).a, 1.0);
+    }
+}
@@ -0,0 +1,295 @@
+use std::{fmt, fs, io, path::Path, str::FromStr};
+
+use crate::color::Rgb;
+use crate::image::{GrayscaleColor, Image};
+use crate::utils::interpolation::smoothstep;
+
+/// How colors are blended between two neighboring stops
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Interpolation {
+    Linear,
+    /// Eases in and out of each stop
+    Smoothstep,
+    /// No blending; each stop's color holds until the next stop
+    Constant,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct ColorStop {
+    pub position: f32,
+    pub color: Rgb,
+}
+
+/**
+ * Maps grayscale values to colors, for turning heightmaps into something that 
+ * looks like terrain (or anything else).
+ * 
+ * Ramps can be loaded from a simple text format: one stop per line, as a 
+ * position followed by either a hex color or three 0..1 channel values, plus an
+ * optional "interpolation" line. Blank lines and comments starting with # are
+ * ignored:
+ * 
+ * ```text
+ * interpolation smoothstep
+ * 0.0  #1a3a6b   # deep water
+ * 0.35 0.88 0.83 0.6
+ * 1.0  #ffffff
+ * ```
+ */
+#[derive(Debug, Clone, PartialEq)]
+pub struct ColorRamp {
+    stops: Vec<ColorStop>,
+    interpolation: Interpolation,
+}
+
+impl ColorRamp {
+
+    pub fn new(mut stops: Vec<ColorStop>, interpolation: Interpolation) -> Self {
+        assert!(!stops.is_empty(), "A color ramp needs at least one stop");
+        assert!(stops.iter().all(|stop| stop.position.is_finite()), "Color stop positions must be finite");
+        assert!(stops.iter().all(|stop| [stop.color.r, stop.color.g, stop.color.b].iter().all(|c| c.is_finite())),
+            "Color stop channels must be finite");
+        stops.sort_by(|a, b| a.position.partial_cmp(&b.position).unwrap());
+
+        ColorRamp { stops, interpolation }
+    }
+
+    pub fn stops(&self) -> &[ColorStop] {
+        &self.stops
+    }
+
+    pub fn interpolation(&self) -> Interpolation {
+        self.interpolation
+    }
+
+    /// Values outside the first and last stops get those stops' colors, and NaN gets the first stop's
+    pub fn color_at(&self, value: GrayscaleColor) -> Rgb {
+        let first = &self.stops[0];
+        let last = &self.stops[self.stops.len() - 1];
+
+        if value.is_nan() || value <= first.position {
+            return first.color;
+        }
+        if value >= last.position {
+            return last.color;
+        }
+
+        let next_index = self.stops.iter().position(|stop| stop.position > value).unwrap();
+        let previous = &self.stops[next_index - 1];
+        let next = &self.stops[next_index];
+
+        let t = (value - previous.position) / (next.position - previous.position);
+        let t = match self.interpolation {
+            Interpolation::Linear => t,
+            Interpolation::Smoothstep => smoothstep(0.0, 1.0, t),
+            Interpolation::Constant => 0.0,
+        };
+
+        previous.color * (1.0 - t) + next.color * t
+    }
+
+    pub fn colorize(&self, image: &Image<GrayscaleColor>) -> Image<Rgb> {
+        image.map(|value| self.color_at(value))
+    }
+
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, ColorRampError> {
+        fs::read_to_string(path)?.parse()
+    }
+
+    /// Looks up one of the built-in ramps by name
+    pub fn preset(name: &str) -> Option<Self> {
+        match name {
+            "terrain" => Some(Self::terrain()),
+            "viridis" => Some(Self::viridis()),
+            "magma" => Some(Self::magma()),
+            "grayscale" => Some(Self::grayscale()),
+            _ => None,
+        }
+    }
+
+    /// Water, then beach, grass, forest, rock and snow
+    pub fn terrain() -> Self {
+        from_hex_stops(&[
+            (0.0, "#1a3a6b"),
+            (0.3, "#3a7bbf"),
+            (0.35, "#e0d49a"),
+            (0.45, "#5a9e3a"),
+            (0.6, "#2f6b2a"),
+            (0.75, "#7a6a58"),
+            (0.9, "#ffffff"),
+        ], Interpolation::Smoothstep)
+    }
+
+    pub fn viridis() -> Self {
+        from_hex_stops(&[
+            (0.0, "#440154"),
+            (0.125, "#472d7b"),
+            (0.25, "#3b528b"),
+            (0.375, "#2c728e"),
+            (0.5, "#21918c"),
+            (0.625, "#28ae80"),
+            (0.75, "#5ec962"),
+            (0.875, "#addc30"),
+            (1.0, "#fde725"),
+        ], Interpolation::Linear)
+    }
+
+    pub fn magma() -> Self {
+        from_hex_stops(&[
+            (0.0, "#000004"),
+            (0.125, "#1c1044"),
+            (0.25, "#4f127b"),
+            (0.375, "#812581"),
+            (0.5, "#b5367a"),
+            (0.625, "#e55064"),
+            (0.75, "#fb8761"),
+            (0.875, "#fec287"),
+            (1.0, "#fcfdbf"),
+        ], Interpolation::Linear)
+    }
+
+    pub fn grayscale() -> Self {
+        from_hex_stops(&[
+            (0.0, "#000000"),
+            (1.0, "#ffffff"),
+        ], Interpolation::Linear)
+    }
+}
+
+fn from_hex_stops(stops: &[(f32, &str)], interpolation: Interpolation) -> ColorRamp {
+    ColorRamp::new(
+        stops.iter().map(|&(position, hex)| ColorStop { position, color: parse_hex(hex).unwrap() }).collect(),
+        interpolation
+    )
+}
+
+fn parse_hex(hex: &str) -> Option<Rgb> {
+    let hex = hex.strip_prefix('#')?;
+    if hex.len() != 6 {
+        return None;
+    }
+
+    let channel = |i: usize| u8::from_str_radix(hex.get(i..i + 2)?, 16).ok().map(|c| c as f32 / 255.0);
+    Some(Rgb::new(channel(0)?, channel(2)?, channel(4)?))
+}
+
+/// Positions have to be finite, or there'd be no telling where the stop goes; "nan" and "inf" parse as f32s
+fn parse_position(word: &str) -> Option<f32> {
+    word.parse::<f32>().ok().filter(|position| position.is_finite())
+}
+
+impl FromStr for ColorRamp {
+    type Err = ColorRampError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut stops = Vec::new();
+        let mut interpolation = Interpolation::Linear;
+
+        for (index, line) in s.lines().enumerate() {
+            let line_number = index + 1;
+            let error = |message: &str| ColorRampError::Parse { line: line_number, message: message.to_string() };
+
+            // a word starting with # is a hex color if it directly follows the
+            // position, otherwise it starts a comment
+            let words: Vec<&str> = line.split_whitespace().enumerate()
+                .take_while(|&(i, word)| i == 1 || !word.starts_with('#'))
+                .map(|(_, word)| word)
+                .collect();
+
+            match words.as_slice() {
+                [] => {},
+                ["interpolation", mode] => {
+                    interpolation = match *mode {
+                        "linear" => Interpolation::Linear,
+                        "smoothstep" => Interpolation::Smoothstep,
+                        "constant" => Interpolation::Constant,
+                        _ => return Err(error("interpolation must be linear, smoothstep or constant")),
+                    };
+                },
+                [position, hex] if hex.starts_with('#') => {
+                    let position = parse_position(position).ok_or_else(|| error("invalid position"))?;
+                    let color = parse_hex(hex).ok_or_else(|| error("invalid hex color"))?;
+                    stops.push(ColorStop { position, color });
+                },
+                [position, r, g, b] => {
+                    let position = parse_position(position).ok_or_else(|| error("invalid position"))?;
+                    let parse = |word: &str| word.parse::<f32>().ok()
+                        .filter(|channel| channel.is_finite())
+                        .ok_or_else(|| error("invalid number"));
+                    stops.push(ColorStop { position, color: Rgb::new(parse(r)?, parse(g)?, parse(b)?) });
+                },
+                _ => return Err(error("expected a position followed by a color")),
+            }
+        }
+
+        if stops.is_empty() {
+            return Err(ColorRampError::Parse { line: 0, message: "no color stops".to_string() });
+        }
+
+        Ok(ColorRamp::new(stops, interpolation))
+    }
+}
+
+#[derive(Debug)]
+pub enum ColorRampError {
+    Io(io::Error),
+    Parse { line: usize, message: String },
+}
+
+impl fmt::Display for ColorRampError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ColorRampError::Io(error) => write!(f, "couldn't read color ramp: {}", error),
+            ColorRampError::Parse { line, message } => write!(f, "invalid color ramp (line {}): {}", line, message),
+        }
+    }
+}
+
+impl std::error::Error for ColorRampError {}
+
+impl From<io::Error> for ColorRampError {
+    fn from(error: io::Error) -> Self {
+        ColorRampError::Io(error)
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::{ColorRamp, Interpolation};
+    use crate::color::Rgb;
+
+    #[test]
+    fn test_parse() {
+        let ramp: ColorRamp = "
+            # sea to snow
+            interpolation constant
+            1.0 1 1 1
+            0.0 #000080   # navy
+        ".parse().unwrap();
+
+        assert_eq!(ramp.interpolation(), Interpolation::Constant);
+        assert_eq!(ramp.stops().len(), 2);
+        assert_eq!(ramp.color_at(0.5), Rgb::new(0.0, 0.0, 128.0 / 255.0));
+        assert_eq!(ramp.color_at(2.0), Rgb::new(1.0, 1.0, 1.0));
+
+        assert!("0.0 #12345".parse::<ColorRamp>().is_err());
+        assert!("interpolation cubic\n0.0 0 0 0".parse::<ColorRamp>().is_err());
+        assert!("nan #000000".parse::<ColorRamp>().is_err());
+        assert!("0.0 #000000\ninf 1 1 1".parse::<ColorRamp>().is_err());
+        assert!("0.0 nan 0 0".parse::<ColorRamp>().is_err());
+        assert!("0.0 0 0 0\n1.0 1 inf 1".parse::<ColorRamp>().is_err());
+    }
+
+    #[test]
+    fn test_nan() {
+        let ramp = ColorRamp::grayscale();
+        assert_eq!(ramp.color_at(f32::NAN), Rgb::new(0.0, 0.0, 0.0));
+    }
+
+    #[test]
+    fn test_linear() {
+        let ramp = ColorRamp::grayscale();
+        assert_eq!(ramp.color_at(0.25), Rgb::new(0.25, 0.25, 0.25));
+    }
+}
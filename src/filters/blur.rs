@@ -0,0 +1,45 @@
+use crate::image::{GrayscaleColor, Image};
+
+/**
+ * Blurs the image with a Gaussian kernel. Radius is the standard deviation of
+ * the kernel, in pixels. Samples past the edges follow the image's edge mode.
+ */
+pub fn apply_gaussian_blur(image: &mut Image<GrayscaleColor>, radius: f32) {
+    assert!(radius >= 0.0, "Radius should be greater than or equal to 0");
+    if radius == 0.0 {
+        return;
+    }
+
+    // three standard deviations covers nearly all of the curve
+    let reach = (radius * 3.0).ceil() as i64;
+    let mut kernel: Vec<f32> = (-reach..=reach)
+        .map(|offset| (-((offset * offset) as f32) / (2.0 * radius * radius)).exp())
+        .collect();
+    let total: f32 = kernel.iter().sum();
+    for weight in kernel.iter_mut() {
+        *weight /= total;
+    }
+
+    // the kernel is separable, so blur horizontally and then vertically
+    let horizontal = convolve(image, &kernel, reach, (1, 0));
+    let vertical = convolve(&horizontal, &kernel, reach, (0, 1));
+    *image = vertical;
+}
+
+fn convolve(image: &Image<GrayscaleColor>, kernel: &[f32], reach: i64, direction: (i64, i64)) -> Image<GrayscaleColor> {
+    let mut result = Image::from_color(image.width(), image.height(), 0.0).with_edge_mode(image.edge_mode());
+
+    for x in 0..image.width() as i64 {
+        for y in 0..image.height() as i64 {
+            let mut sum = 0.0;
+            for (i, weight) in kernel.iter().enumerate() {
+                let offset = i as i64 - reach;
+                sum += weight * *image.get(x + offset * direction.0, y + offset * direction.1);
+            }
+
+            result.set(x, y, sum);
+        }
+    }
+
+    result
+}
@@ -0,0 +1,117 @@
+use serde::{Deserialize, Serialize};
+
+use crate::image::{GrayscaleColor, Image};
+use crate::sampling::SampleFilter;
+use crate::utils::vec2::Vec2;
+
+/**
+ * Parameters for domain warping, which reads each pixel from somewhere else in
+ * the image, offset by other noise. It's the usual way to turn regular-looking
+ * noise into something organic, like swirling marble or eroded ridges.
+ */
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct DomainWarpParams {
+    /// Largest offset, in pixels
+    pub strength: f32,
+    /// How many times the displacement is fed back into itself; 1 is a plain
+    /// warp, and more give the f(p + f(p + f(p))) look
+    pub iterations: usize,
+    pub filter: SampleFilter,
+}
+
+impl Default for DomainWarpParams {
+
+    fn default() -> Self {
+        DomainWarpParams {
+            strength: 32.0,
+            iterations: 1,
+            filter: SampleFilter::Bilinear,
+        }
+    }
+}
+
+/**
+ * Warps the image using two displacement images, one per axis. Displacement
+ * values are centered on 0.5, so 0.5 means no offset and 0 and 1 mean the
+ * full strength in either direction. Both must be the same size as the image;
+ * Perlin noise or diamond-square output with a 0.5 midpoint works well.
+ */
+pub fn apply_domain_warp(image: &mut Image<GrayscaleColor>, displacement_x: &Image<GrayscaleColor>, displacement_y: &Image<GrayscaleColor>, params: &DomainWarpParams) {
+    assert!(displacement_x.width() == image.width() && displacement_x.height() == image.height(), "Displacement must be the same size as the image");
+    assert!(displacement_y.width() == image.width() && displacement_y.height() == image.height(), "Displacement must be the same size as the image");
+
+    apply_domain_warp_field(image, |position| Vec2 {
+        x: (displacement_x.sample(position.x, position.y, params.filter) - 0.5) * 2.0,
+        y: (displacement_y.sample(position.x, position.y, params.filter) - 0.5) * 2.0,
+    }, params);
+}
+
+/**
+ * Warps the image using a displacement field, which gives an offset (scaled by
+ * strength) for any position in pixel coordinates. Each iteration evaluates
+ * the field at the position the previous iteration displaced to.
+ */
+pub fn apply_domain_warp_field<F: Fn(Vec2) -> Vec2>(image: &mut Image<GrayscaleColor>, field: F, params: &DomainWarpParams) {
+    let mut warped = Image::from_color(image.width(), image.height(), 0.0).with_edge_mode(image.edge_mode());
+
+    for x in 0..image.width() as i64 {
+        for y in 0..image.height() as i64 {
+            let position = Vec2 { x: x as f32, y: y as f32 };
+
+            let mut offset = Vec2::new();
+            for _ in 0..params.iterations {
+                offset = &field(&position + &offset) * params.strength;
+            }
+
+            let source = &position + &offset;
+            warped.set(x, y, image.sample(source.x, source.y, params.filter));
+        }
+    }
+
+    *image = warped;
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::{apply_domain_warp, apply_domain_warp_field, DomainWarpParams};
+    use crate::image::Image;
+    use crate::sampling::SampleFilter;
+    use crate::utils::vec2::Vec2;
+
+    fn ramp() -> Image<f32> {
+        let mut image = Image::from_color(8, 8, 0.0);
+        for x in 0..8 {
+            for y in 0..8 {
+                image.set(x, y, x as f32);
+            }
+        }
+        image
+    }
+
+    #[test]
+    fn test_neutral_displacement() {
+        let mut image = ramp();
+        let neutral = Image::from_color(8, 8, 0.5);
+
+        apply_domain_warp(&mut image, &neutral, &neutral, &DomainWarpParams::default());
+
+        for x in 0..8 {
+            assert_eq!(*image.get(x, 3), x as f32);
+        }
+    }
+
+    #[test]
+    fn test_constant_shift() {
+        let mut image = ramp();
+        let params = DomainWarpParams { strength: 1.0, iterations: 3, filter: SampleFilter::Nearest };
+
+        // a constant field shifts by the same amount no matter how many iterations
+        apply_domain_warp_field(&mut image, |_| Vec2 { x: 2.0, y: 0.0 }, &params);
+
+        assert_eq!(*image.get(0, 0), 2.0);
+        assert_eq!(*image.get(5, 0), 7.0);
+        assert_eq!(*image.get(6, 0), 0.0);
+    }
+}
@@ -0,0 +1,262 @@
+extern crate rand;
+use rand::Rng;
+
+use serde::{Deserialize, Serialize};
+
+use crate::image::{GrayscaleColor, Image};
+use crate::utils::random::seeded_rng;
+use crate::utils::vec2::Vec2;
+
+/**
+ * Parameters for particle-based hydraulic erosion. Each droplet is dropped at
+ * a random point, rolls downhill picking up sediment while it speeds up, and
+ * drops it again when it slows down or evaporates.
+ */
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct HydraulicErosionParams {
+    /// Number of droplets to simulate
+    pub droplets: usize,
+    /// From 0 to 1; how much a droplet keeps its direction instead of following the slope
+    pub inertia: f32,
+    /// Multiplier for how much sediment a droplet can carry
+    pub sediment_capacity: f32,
+    /// Capacity floor, so droplets on flat ground can still carry some sediment
+    pub min_sediment_capacity: f32,
+    /// From 0 to 1; how much surplus sediment is dropped each step
+    pub deposition_rate: f32,
+    /// From 0 to 1; how much of the remaining capacity is filled by eroding each step
+    pub erosion_rate: f32,
+    /// From 0 to 1; how much water evaporates each step
+    pub evaporation_rate: f32,
+    pub gravity: f32,
+    /// Maximum number of steps a droplet is simulated for
+    pub max_lifetime: usize,
+    pub seed: u64,
+}
+
+impl Default for HydraulicErosionParams {
+
+    fn default() -> Self {
+        HydraulicErosionParams {
+            droplets: 70_000,
+            inertia: 0.05,
+            sediment_capacity: 4.0,
+            min_sediment_capacity: 0.01,
+            deposition_rate: 0.3,
+            erosion_rate: 0.3,
+            evaporation_rate: 0.01,
+            gravity: 4.0,
+            max_lifetime: 30,
+            seed: 0,
+        }
+    }
+}
+
+/// How much material was deposited onto and eroded from each pixel
+pub struct ErosionMaps {
+    pub deposition: Image<GrayscaleColor>,
+    pub erosion: Image<GrayscaleColor>,
+}
+
+pub fn apply_hydraulic_erosion(image: &mut Image<GrayscaleColor>, params: &HydraulicErosionParams) {
+    erode(image, params, None);
+}
+
+/**
+ * Same as apply_hydraulic_erosion, but also records where material was moved
+ * from and to, which is useful for texturing.
+ */
+pub fn apply_hydraulic_erosion_with_maps(image: &mut Image<GrayscaleColor>, params: &HydraulicErosionParams) -> ErosionMaps {
+    let mut maps = ErosionMaps {
+        deposition: Image::from_color(image.width(), image.height(), 0.0),
+        erosion: Image::from_color(image.width(), image.height(), 0.0),
+    };
+
+    erode(image, params, Some(&mut maps));
+    maps
+}
+
+fn erode(image: &mut Image<GrayscaleColor>, params: &HydraulicErosionParams, mut maps: Option<&mut ErosionMaps>) {
+    assert!(image.width() >= 2 && image.height() >= 2, "Image must be at least 2x2");
+    assert!(params.inertia >= 0.0 && params.inertia <= 1.0, "Inertia must be between 0 and 1");
+
+    let mut rng = seeded_rng(params.seed);
+    let max_x = (image.width() - 1) as f32;
+    let max_y = (image.height() - 1) as f32;
+
+    for _ in 0..params.droplets {
+        let mut droplet = Droplet::new(Vec2 { x: rng.gen_range(0.0, max_x), y: rng.gen_range(0.0, max_y) });
+
+        for _ in 0..params.max_lifetime {
+            if !droplet.step(image, params, maps.as_deref_mut()) {
+                break;
+            }
+        }
+    }
+}
+
+struct Droplet {
+    position: Vec2,
+    direction: Vec2,
+    speed: f32,
+    water: f32,
+    sediment: f32,
+}
+
+impl Droplet {
+
+    fn new(position: Vec2) -> Self {
+        Droplet { position, direction: Vec2::new(), speed: 1.0, water: 1.0, sediment: 0.0 }
+    }
+
+    /// Moves the droplet one pixel, eroding or depositing as it goes. Returns false once it has stopped or run off the map.
+    fn step(&mut self, image: &mut Image<GrayscaleColor>, params: &HydraulicErosionParams, maps: Option<&mut ErosionMaps>) -> bool {
+        let max_x = (image.width() - 1) as f32;
+        let max_y = (image.height() - 1) as f32;
+
+        let old_position = self.position;
+        let (height, gradient) = height_and_gradient(image, &old_position);
+
+        // blend the previous direction with the downhill direction
+        self.direction = &(&self.direction * params.inertia) - &(&gradient * (1.0 - params.inertia));
+        if self.direction.len() == 0.0 {
+            return false;
+        }
+        self.direction.normalize();
+        self.position = &self.position + &self.direction;
+
+        // droplets that run off the map are lost, along with their sediment
+        if self.position.x < 0.0 || self.position.x >= max_x || self.position.y < 0.0 || self.position.y >= max_y {
+            return false;
+        }
+
+        let (new_height, _) = height_and_gradient(image, &self.position);
+        let delta_height = new_height - height;
+
+        let capacity = (-delta_height * self.speed * self.water * params.sediment_capacity).max(params.min_sediment_capacity);
+
+        if self.sediment > capacity || delta_height > 0.0 {
+            // moving uphill fills in the pit behind the droplet; otherwise drop the surplus
+            let amount = if delta_height > 0.0 {
+                delta_height.min(self.sediment)
+            } else {
+                (self.sediment - capacity) * params.deposition_rate
+            };
+
+            self.sediment -= amount;
+            spread(image, &old_position, amount);
+            if let Some(maps) = maps {
+                spread(&mut maps.deposition, &old_position, amount);
+            }
+        } else {
+            // never dig deeper than the height difference, or we'd carve holes
+            let amount = ((capacity - self.sediment) * params.erosion_rate).min(-delta_height);
+
+            self.sediment += amount;
+            spread(image, &old_position, -amount);
+            if let Some(maps) = maps {
+                spread(&mut maps.erosion, &old_position, amount);
+            }
+        }
+
+        // delta_height is negative going downhill, which is when the droplet speeds up
+        self.speed = (self.speed * self.speed - delta_height * params.gravity).max(0.0).sqrt();
+        self.water *= 1.0 - params.evaporation_rate;
+
+        true
+    }
+}
+
+/// Bilinearly interpolated height and gradient at a position
+fn height_and_gradient(image: &Image<GrayscaleColor>, position: &Vec2) -> (f32, Vec2) {
+    let x = position.x.floor() as i64;
+    let y = position.y.floor() as i64;
+    let u = position.x - x as f32;
+    let v = position.y - y as f32;
+
+    let top_left = *image.get(x, y);
+    let top_right = *image.get(x + 1, y);
+    let bottom_left = *image.get(x, y + 1);
+    let bottom_right = *image.get(x + 1, y + 1);
+
+    let gradient = Vec2 {
+        x: (top_right - top_left) * (1.0 - v) + (bottom_right - bottom_left) * v,
+        y: (bottom_left - top_left) * (1.0 - u) + (bottom_right - top_right) * u,
+    };
+
+    let height = top_left * (1.0 - u) * (1.0 - v)
+        + top_right * u * (1.0 - v)
+        + bottom_left * (1.0 - u) * v
+        + bottom_right * u * v;
+
+    (height, gradient)
+}
+
+/// Adds amount to the four pixels around position, weighted by proximity
+fn spread(image: &mut Image<GrayscaleColor>, position: &Vec2, amount: f32) {
+    let x = position.x.floor() as i64;
+    let y = position.y.floor() as i64;
+    let u = position.x - x as f32;
+    let v = position.y - y as f32;
+
+    image.add(x, y, amount * (1.0 - u) * (1.0 - v));
+    image.add(x + 1, y, amount * u * (1.0 - v));
+    image.add(x, y + 1, amount * (1.0 - u) * v);
+    image.add(x + 1, y + 1, amount * u * v);
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::{apply_hydraulic_erosion_with_maps, Droplet, HydraulicErosionParams};
+    use crate::generators::generate_diamond_square;
+    use crate::image::{GrayscaleColor, Image};
+    use crate::utils::vec2::Vec2;
+
+    #[test]
+    fn test_conserves_material() {
+        let mut image = generate_diamond_square(65, 1.0, 0.8, 5);
+        let params = HydraulicErosionParams { droplets: 2000, ..HydraulicErosionParams::default() };
+
+        let total_before = total(&image);
+        let maps = apply_hydraulic_erosion_with_maps(&mut image, &params);
+        let total_after = total(&image);
+
+        // whatever didn't run off the edge was put back down somewhere
+        let lost = total(&maps.erosion) - total(&maps.deposition);
+        assert!(lost >= 0.0);
+        assert!((total_before - lost - total_after).abs() < 0.01);
+    }
+
+    #[test]
+    fn test_speeds_up_downhill() {
+        // a steady slope falling to the right
+        let mut image = Image::from_color(64, 16, 0.0);
+        for x in 0..64 {
+            for y in 0..16 {
+                image.set(x, y, 1.0 - x as f32 * 0.01);
+            }
+        }
+
+        let params = HydraulicErosionParams::default();
+        let mut droplet = Droplet::new(Vec2 { x: 2.5, y: 8.5 });
+
+        for _ in 0..20 {
+            let (speed, sediment) = (droplet.speed, droplet.sediment);
+            assert!(droplet.step(&mut image, &params, None));
+            assert!(droplet.speed > speed, "speed went from {} to {}", speed, droplet.speed);
+            assert!(droplet.sediment > sediment, "sediment went from {} to {}", sediment, droplet.sediment);
+        }
+    }
+
+    fn total(image: &Image<GrayscaleColor>) -> f32 {
+        let mut sum = 0.0;
+        for x in 0..image.width() as i64 {
+            for y in 0..image.height() as i64 {
+                sum += *image.get(x, y);
+            }
+        }
+        sum
+    }
+}
@@ -0,0 +1,11 @@
+mod hydraulic_erosion;
+mod thermal_erosion;
+mod normal_map;
+mod blur;
+mod domain_warp;
+
+pub use hydraulic_erosion::{HydraulicErosionParams, ErosionMaps, apply_hydraulic_erosion, apply_hydraulic_erosion_with_maps};
+pub use thermal_erosion::{ThermalErosionParams, apply_thermal_erosion};
+pub use normal_map::{NormalMapMethod, NormalMapFormat, generate_normal_map};
+pub use blur::apply_gaussian_blur;
+pub use domain_warp::{DomainWarpParams, apply_domain_warp, apply_domain_warp_field};
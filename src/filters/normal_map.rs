@@ -0,0 +1,95 @@
+use serde::{Deserialize, Serialize};
+
+use crate::image::{FloatColor, GrayscaleColor, Image};
+use crate::utils::vec3::Vec3;
+
+#[derive(Debug, Copy, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum NormalMapMethod {
+    /// Smoother; considers all eight neighbors
+    Sobel,
+    /// Sharper; considers only the four direct neighbors
+    CentralDifference,
+}
+
+/// Which way the green channel points, which differs between engines
+#[derive(Debug, Copy, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum NormalMapFormat {
+    /// Green is up
+    #[serde(rename = "opengl")]
+    OpenGl,
+    /// Green is down
+    #[serde(rename = "directx")]
+    DirectX,
+}
+
+/**
+ * Creates a tangent-space normal map from a heightmap, with each normal's 
+ * components mapped from -1..1 into 0..1 colors. Strength exaggerates (> 1) or 
+ * flattens (< 1) the slopes. Neighbors past the edges follow the heightmap's 
+ * edge mode; with the default wrap-around, the normal map of a tileable 
+ * heightmap is tileable too.
+ */
+pub fn generate_normal_map(heightmap: &Image<GrayscaleColor>, strength: f32, method: NormalMapMethod, format: NormalMapFormat) -> Image<FloatColor> {
+    let mut normal_map = Image::from_color(heightmap.width(), heightmap.height(), FloatColor { r: 0.5, g: 0.5, b: 1.0 });
+
+    for x in 0..heightmap.width() as i64 {
+        for y in 0..heightmap.height() as i64 {
+            let h = |dx: i64, dy: i64| *heightmap.get(x + dx, y + dy);
+
+            // slope along the image's x axis (right) and y axis (down)
+            let (slope_x, slope_y) = match method {
+                NormalMapMethod::Sobel => (
+                    ((h(1, -1) + 2.0 * h(1, 0) + h(1, 1)) - (h(-1, -1) + 2.0 * h(-1, 0) + h(-1, 1))) / 8.0,
+                    ((h(-1, 1) + 2.0 * h(0, 1) + h(1, 1)) - (h(-1, -1) + 2.0 * h(0, -1) + h(1, -1))) / 8.0,
+                ),
+                NormalMapMethod::CentralDifference => (
+                    (h(1, 0) - h(-1, 0)) / 2.0,
+                    (h(0, 1) - h(0, -1)) / 2.0,
+                ),
+            };
+
+            let normal_y = match format {
+                NormalMapFormat::OpenGl => slope_y * strength,
+                NormalMapFormat::DirectX => -slope_y * strength,
+            };
+            let normal = Vec3 { x: -slope_x * strength, y: normal_y, z: 1.0 }.normalized();
+
+            normal_map.set(x, y, FloatColor {
+                r: normal.x * 0.5 + 0.5,
+                g: normal.y * 0.5 + 0.5,
+                b: normal.z * 0.5 + 0.5,
+            });
+        }
+    }
+
+    normal_map
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::{generate_normal_map, NormalMapFormat, NormalMapMethod};
+    use crate::image::Image;
+
+    #[test]
+    fn test_slope_directions() {
+        // rises to the right and towards the bottom
+        let mut heightmap = Image::from_color(8, 8, 0.0);
+        for x in 0..8 {
+            for y in 0..8 {
+                heightmap.set(x, y, (x + y) as f32 * 0.1);
+            }
+        }
+
+        let open_gl = generate_normal_map(&heightmap, 1.0, NormalMapMethod::Sobel, NormalMapFormat::OpenGl);
+        let direct_x = generate_normal_map(&heightmap, 1.0, NormalMapMethod::Sobel, NormalMapFormat::DirectX);
+
+        let normal = open_gl.get(3, 3);
+        assert!(normal.r < 0.5);
+        assert!(normal.g > 0.5);
+        assert!(normal.b > 0.5);
+        assert_eq!(direct_x.get(3, 3).g, 1.0 - normal.g);
+    }
+}
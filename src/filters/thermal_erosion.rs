@@ -0,0 +1,118 @@
+use serde::{Deserialize, Serialize};
+
+use crate::image::{GrayscaleColor, Image};
+
+/**
+ * Parameters for thermal erosion, which simulates loose material crumbling 
+ * down any slope that's steeper than it can rest at.
+ */
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ThermalErosionParams {
+    pub iterations: usize,
+    /// Steepest stable slope, as height difference per pixel (the tangent of the talus angle)
+    pub talus: f32,
+    /// From 0 to 1; how much of the excess material moves each iteration
+    pub rate: f32,
+}
+
+impl Default for ThermalErosionParams {
+
+    fn default() -> Self {
+        ThermalErosionParams {
+            iterations: 50,
+            talus: 0.004,
+            rate: 0.5,
+        }
+    }
+}
+
+const NEIGHBORS: [(i64, i64); 8] = [
+    (-1, -1), (0, -1), (1, -1),
+    (-1,  0),          (1,  0),
+    (-1,  1), (0,  1), (1,  1),
+];
+
+/**
+ * Repeatedly moves material from each pixel to any lower neighbors it's too 
+ * steep relative to, until slopes settle towards the talus angle. Every pixel 
+ * is updated at once each iteration, so the result doesn't depend on the order
+ * pixels are visited in.
+ */
+pub fn apply_thermal_erosion(image: &mut Image<GrayscaleColor>, params: &ThermalErosionParams) {
+    assert!(params.talus >= 0.0, "Talus should be greater than or equal to 0");
+    assert!(params.rate >= 0.0 && params.rate <= 1.0, "Rate must be between 0 and 1");
+
+    let width = image.width() as i64;
+    let height = image.height() as i64;
+
+    for _ in 0..params.iterations {
+        let mut changes = Image::from_color(image.width(), image.height(), 0.0).with_edge_mode(image.edge_mode());
+
+        for x in 0..width {
+            for y in 0..height {
+                let current = *image.get(x, y);
+
+                // how far above its stable height the pixel is, relative to each neighbor
+                let mut excesses = [0.0; 8];
+                let mut total_excess = 0.0;
+                let mut max_excess: f32 = 0.0;
+                for (i, (dx, dy)) in NEIGHBORS.iter().enumerate() {
+                    let distance = if *dx != 0 && *dy != 0 { std::f32::consts::SQRT_2 } else { 1.0 };
+                    let excess = current - *image.get(x + dx, y + dy) - params.talus * distance;
+
+                    if excess > 0.0 {
+                        excesses[i] = excess;
+                        total_excess += excess;
+                        max_excess = max_excess.max(excess);
+                    }
+                }
+
+                if total_excess > 0.0 {
+                    // only move half the excess at most, so the pixel can't end 
+                    // up lower than the neighbor it's shedding onto
+                    let moved = max_excess * params.rate / 2.0;
+
+                    changes.add(x, y, -moved);
+                    for (i, (dx, dy)) in NEIGHBORS.iter().enumerate() {
+                        if excesses[i] > 0.0 {
+                            changes.add(x + dx, y + dy, moved * excesses[i] / total_excess);
+                        }
+                    }
+                }
+            }
+        }
+
+        for x in 0..width {
+            for y in 0..height {
+                image.add(x, y, *changes.get(x, y));
+            }
+        }
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::{apply_thermal_erosion, ThermalErosionParams};
+    use crate::image::Image;
+
+    #[test]
+    fn test_spike_slumps() {
+        let mut image = Image::from_color(9, 9, 0.0);
+        image.set(4, 4, 1.0);
+
+        apply_thermal_erosion(&mut image, &ThermalErosionParams { iterations: 100, talus: 0.05, rate: 0.5 });
+
+        let mut total = 0.0;
+        for x in 0..9 {
+            for y in 0..9 {
+                total += *image.get(x, y);
+            }
+        }
+
+        assert!(*image.get(4, 4) < 0.5);
+        assert!(*image.get(4, 4) >= *image.get(3, 4));
+        assert!((total - 1.0).abs() < 0.0001);
+    }
+}
@@ -1,6 +1,26 @@
 
+use serde::{Deserialize, Serialize};
+
 use crate::image::{GrayscaleColor, Image};
 use crate::utils::vec2::Vec2;
+use super::Generator;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BricksParams {
+    pub bricks_x: usize,
+    pub bricks_y: usize,
+    pub bevel: Vec2,
+    pub gap: Vec2,
+}
+
+impl Generator for BricksParams {
+
+    fn generate(&self, width: usize, height: usize) -> Image<GrayscaleColor> {
+        let mut image = Image::from_color(width, height, 0.0);
+        apply_bricks(&mut image, self.bricks_x, self.bricks_y, self.bevel, self.gap);
+        image
+    }
+}
 
 pub fn generate_bricks(size: usize, bricks_x: usize, bricks_y: usize, bevel: Vec2, gap: Vec2) -> Image<GrayscaleColor> {
     let mut image = Image::from_color(size, size, 0.0);
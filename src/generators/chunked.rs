@@ -0,0 +1,224 @@
+use serde::{Deserialize, Serialize};
+
+use crate::image::{GrayscaleColor, Image};
+use crate::noise::NoiseFn;
+use crate::utils::random::{hash_coords, hash_to_unit};
+use crate::utils::vec2::Vec2;
+use super::diamond_square::variance_scale;
+
+/**
+ * Renders one rectangular window of an endless noise field, for worlds too big
+ * to generate in one piece. The window's top-left pixel is at (x, y) in world
+ * pixel coordinates, and feature size is the width of one noise unit in pixels.
+ *
+ * Every pixel's value depends only on its world coordinates, so chunks can be
+ * rendered separately, in any order, and adjacent chunks match exactly along
+ * their borders. Noise values from -1 to 1 are mapped into 0..1.
+ */
+pub fn render_noise_chunk<N: NoiseFn>(noise: &N, x: i64, y: i64, width: usize, height: usize, feature_size: f32) -> Image<GrayscaleColor> {
+    assert!(feature_size > 0.0, "Feature size must be greater than 0");
+
+    let mut image = Image::from_color(width, height, 0.0);
+
+    for chunk_x in 0..width as i64 {
+        for chunk_y in 0..height as i64 {
+            let position = Vec2 {
+                x: (x + chunk_x) as f32 / feature_size,
+                y: (y + chunk_y) as f32 / feature_size,
+            };
+
+            image.set(chunk_x, chunk_y, noise.sample(position) * 0.5 + 0.5);
+        }
+    }
+
+    image
+}
+
+/**
+ * Diamond-square-style midpoint displacement over an endless world. Instead of
+ * drawing offsets from an RNG in order, each point's offset is hashed from its
+ * world coordinates, so any window can be rendered on its own and neighboring
+ * windows agree exactly.
+ *
+ * Feature size is the spacing, in pixels, of the coarsest random heights, and
+ * must be a power of two. Variance and coarseness work as in
+ * apply_diamond_square, with feature size standing in for the image size, so
+ * a feature-size window has the same amplitude at every scale as a
+ * diamond-square image of that size. The coarsest heights, which
+ * diamond-square takes from the image's corners, are offset by the full
+ * variance around 0.5.
+ */
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct MidpointDisplacementParams {
+    pub feature_size: usize,
+    pub variance: f32,
+    pub coarseness: f32,
+    pub seed: u64,
+}
+
+impl MidpointDisplacementParams {
+
+    /**
+     * Renders the window whose top-left pixel is at (x, y) in world pixel
+     * coordinates.
+     *
+     * Points near the edge of a calculation are missing some of their
+     * neighbors, and the error spreads inwards by up to one feature size. So
+     * the calculation covers the window plus a margin of one feature size on
+     * every side, aligned to the coarsest grid, and only the window is kept.
+     */
+    pub fn render_chunk(&self, x: i64, y: i64, width: usize, height: usize) -> Image<GrayscaleColor> {
+        assert!(self.feature_size.is_power_of_two(), "Feature size must be a power of two");
+        assert!(self.variance >= 0.0, "Variance should be greater than or equal to 0");
+        assert!(self.variance <= 1.0, "Variance should be less than or equal to 1");
+        assert!(self.coarseness >= 0.0 && self.coarseness <= 1.0, "Coarseness must be between 0 and 1");
+
+        let spacing = self.feature_size as i64;
+        let min_x = x.div_euclid(spacing) * spacing - spacing;
+        let min_y = y.div_euclid(spacing) * spacing - spacing;
+        let max_x = (x + width as i64 - 1).div_euclid(spacing) * spacing + spacing * 2;
+        let max_y = (y + height as i64 - 1).div_euclid(spacing) * spacing + spacing * 2;
+
+        // the region's edges fall on the coarsest grid, so local coordinates
+        // line up with the grid the same way world coordinates do
+        let mut region = Region {
+            min_x,
+            min_y,
+            width: max_x - min_x + 1,
+            height: max_y - min_y + 1,
+            values: vec![0.0; ((max_x - min_x + 1) * (max_y - min_y + 1)) as usize],
+        };
+
+        for local_x in (0..region.width).step_by(self.feature_size) {
+            for local_y in (0..region.height).step_by(self.feature_size) {
+                let value = 0.5 + self.offset(&region, local_x, local_y, 0, self.variance);
+                region.set(local_x, local_y, value);
+            }
+        }
+
+        let mut step = spacing;
+        let mut running_variance = self.variance;
+        while step >= 2 {
+            let half = step / 2;
+            let variance = running_variance * variance_scale(spacing / step, spacing);
+
+            // diamond: the center of every square gets the average of its corners
+            for local_x in (half..region.width).step_by(step as usize) {
+                for local_y in (half..region.height).step_by(step as usize) {
+                    let average = region.average(&[
+                        (local_x - half, local_y - half),
+                        (local_x + half, local_y - half),
+                        (local_x - half, local_y + half),
+                        (local_x + half, local_y + half),
+                    ]);
+                    region.set(local_x, local_y, average + self.offset(&region, local_x, local_y, step, variance));
+                }
+            }
+
+            // square: the middle of every edge gets the average of its neighbors
+            for local_x in (0..region.width).step_by(half as usize) {
+                let first_y = if (local_x / half) % 2 == 0 { half } else { 0 };
+
+                for local_y in (first_y..region.height).step_by(step as usize) {
+                    let average = region.average(&[
+                        (local_x - half, local_y),
+                        (local_x + half, local_y),
+                        (local_x, local_y - half),
+                        (local_x, local_y + half),
+                    ]);
+                    region.set(local_x, local_y, average + self.offset(&region, local_x, local_y, step, variance));
+                }
+            }
+
+            step = half;
+            running_variance *= self.coarseness;
+        }
+
+        let mut image = Image::from_color(width, height, 0.0);
+        for chunk_x in 0..width as i64 {
+            for chunk_y in 0..height as i64 {
+                image.set(chunk_x, chunk_y, region.get(x + chunk_x - min_x, y + chunk_y - min_y));
+            }
+        }
+
+        image
+    }
+
+    /// A random offset in -range/2..range/2, hashed from the point's world coordinates
+    fn offset(&self, region: &Region, local_x: i64, local_y: i64, step: i64, range: f32) -> f32 {
+        let hash = hash_coords(self.seed, &[region.min_x + local_x, region.min_y + local_y, step]);
+        (hash_to_unit(hash) - 0.5) * range
+    }
+}
+
+/// The area a chunk is calculated over, in local coordinates
+struct Region {
+    min_x: i64,
+    min_y: i64,
+    width: i64,
+    height: i64,
+    values: Vec<f32>,
+}
+
+impl Region {
+
+    fn get(&self, x: i64, y: i64) -> f32 {
+        self.values[(x + y * self.width) as usize]
+    }
+
+    fn set(&mut self, x: i64, y: i64, value: f32) {
+        self.values[(x + y * self.width) as usize] = value;
+    }
+
+    /// Average of whichever of the points fall inside the region
+    fn average(&self, points: &[(i64, i64)]) -> f32 {
+        let mut sum = 0.0;
+        let mut count = 0;
+        for &(x, y) in points {
+            if x >= 0 && x < self.width && y >= 0 && y < self.height {
+                sum += self.get(x, y);
+                count += 1;
+            }
+        }
+
+        sum / count as f32
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::{render_noise_chunk, MidpointDisplacementParams};
+    use crate::image::Image;
+    use crate::noise::{Fbm, Perlin};
+
+    /// Checks that the chunk at (x, y) matches the same area of whole, which starts at (0, 0)
+    fn assert_matches(chunk: &Image<f32>, whole: &Image<f32>, x: i64, y: i64) {
+        for chunk_x in 0..chunk.width() as i64 {
+            for chunk_y in 0..chunk.height() as i64 {
+                assert_eq!(chunk.get(chunk_x, chunk_y).to_bits(), whole.get(x + chunk_x, y + chunk_y).to_bits(), "at ({}, {})", x + chunk_x, y + chunk_y);
+            }
+        }
+    }
+
+    #[test]
+    fn test_noise_chunks_match() {
+        let noise = Fbm::new(Perlin::new(3), 4);
+        let whole = render_noise_chunk(&noise, -20, -10, 50, 30, 16.0);
+
+        assert_matches(&render_noise_chunk(&noise, -20, -10, 25, 30, 16.0), &whole, 0, 0);
+        assert_matches(&render_noise_chunk(&noise, 5, -10, 25, 15, 16.0), &whole, 25, 0);
+        assert_matches(&render_noise_chunk(&noise, 5, 5, 25, 15, 16.0), &whole, 25, 15);
+    }
+
+    #[test]
+    fn test_midpoint_chunks_match() {
+        let params = MidpointDisplacementParams { feature_size: 16, variance: 1.0, coarseness: 0.6, seed: 9 };
+        let whole = params.render_chunk(-37, -5, 70, 41);
+
+        assert_matches(&params.render_chunk(-37, -5, 35, 20), &whole, 0, 0);
+        assert_matches(&params.render_chunk(-2, -5, 35, 20), &whole, 35, 0);
+        assert_matches(&params.render_chunk(-37, 15, 35, 21), &whole, 0, 20);
+        assert_matches(&params.render_chunk(-2, 15, 35, 21), &whole, 35, 20);
+    }
+}
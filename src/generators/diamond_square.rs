@@ -4,14 +4,42 @@ use std::collections::{HashSet};
 extern crate rand;
 use rand::Rng;
 
-use crate::image::{GrayscaleColor, Image};
+use serde::{Deserialize, Serialize};
+
+use crate::image::{EdgeMode, GrayscaleColor, Image};
+use crate::sampling::SampleFilter;
+use crate::utils::random::{seeded_rng, SeededRng};
+use super::Generator;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DiamondSquareParams {
+    pub variance: f32,
+    pub coarseness: f32,
+    pub seed: u64,
+    /// Makes opposite edges line up, so the image repeats seamlessly
+    #[serde(default)]
+    pub tileable: bool,
+}
+
+impl Generator for DiamondSquareParams {
+
+    fn generate(&self, width: usize, height: usize) -> Image<GrayscaleColor> {
+        let mut image = Image::from_color(width, height, 0.5);
+        if self.tileable {
+            apply_tileable_diamond_square(&mut image, self.variance, self.coarseness, self.seed);
+        } else {
+            apply_diamond_square(&mut image, self.variance, self.coarseness, self.seed);
+        }
+        image
+    }
+}
 
 /**
  * Creates a new Image and runs apply_diamond_square on it. 
  */
-pub fn generate_diamond_square(size: usize, variance: f32, coarseness: f32) -> Image<GrayscaleColor> {
+pub fn generate_diamond_square(size: usize, variance: f32, coarseness: f32, seed: u64) -> Image<GrayscaleColor> {
     let mut image = Image::from_color(size, size, 0.5);
-    apply_diamond_square(&mut image, variance, coarseness);
+    apply_diamond_square(&mut image, variance, coarseness, seed);
     return image;
 }
 
@@ -23,38 +51,126 @@ pub fn generate_diamond_square(size: usize, variance: f32, coarseness: f32) -> I
  * 
  * Coarseness is a smoothing value; smaller values will make the contours more smooth, higher
  * values will make them more "rough".
+ * 
+ * Seed determines every random offset; the same seed always produces the same image.
+ * 
+ * The algorithm itself only works on squares whose size is a power of two plus 
+ * one. Any other image is handled by running it on the smallest such square 
+ * that covers the image, seeded with the image's corner values, and keeping 
+ * the top-left corner of the result.
+ */
+pub fn apply_diamond_square(image: &mut Image<GrayscaleColor>, variance: f32, coarseness: f32, seed: u64) {
+    assert!(variance >= 0.0, "Variance should be greater than or equal to 0");
+    assert!(variance <= 1.0, "Variance should be less than or equal to 1");
+    assert!(coarseness >= 0.0, "Coarseness should be greater than or equal to 0");
+    assert!(coarseness <= 1.0, "Coarseness should be less than or equal to 1");
+    assert!(image.width() > 0 && image.height() > 0, "Image must not be empty");
+
+    let width = image.width();
+    let height = image.height();
+    let size = (width.max(height) - 1).next_power_of_two() + 1;
+
+    // cropping with clamping puts the image's corner values on the square's 
+    // corners; the algorithm itself reads neighbors with wrap-around
+    let edge_mode = image.edge_mode();
+    image.set_edge_mode(EdgeMode::Clamp);
+    let mut square = image.crop(0, 0, size, size).with_edge_mode(EdgeMode::Wrap);
+
+    run(&mut square, (size as i64 - 1, size as i64 - 1), variance, coarseness, seed);
+
+    *image = square.crop(0, 0, width, height).with_edge_mode(edge_mode);
+}
+
+/**
+ * Same as apply_diamond_square, but the result tiles: opposite edges and all 
+ * corners share their values, so the image repeats without seams. The image's 
+ * top-left pixel seeds the first corner.
+ * 
+ * The algorithm works on a wrapped-around lattice whose width and height are 
+ * powers of two, made of square cells as large as the shorter side, so wide 
+ * images get a row of several random corners to start from. Images of exactly 
+ * that size are generated directly. Any other size is generated on the lattice 
+ * closest to its aspect ratio and then resized to fit, which keeps it tileable 
+ * but softens it slightly, and stretches features a little when the aspect 
+ * ratio isn't a power of two.
  */
-pub fn apply_diamond_square(image: &mut Image<GrayscaleColor>, variance: f32, coarseness: f32) {
-    assert!(image.width() == image.height(), "Image width and height must be the same");
-    assert!((image.width() - 1).is_power_of_two(), "Image width/height must be a power of two plus one");
+pub fn apply_tileable_diamond_square(image: &mut Image<GrayscaleColor>, variance: f32, coarseness: f32, seed: u64) {
     assert!(variance >= 0.0, "Variance should be greater than or equal to 0");
     assert!(variance <= 1.0, "Variance should be less than or equal to 1");
     assert!(coarseness >= 0.0, "Coarseness should be greater than or equal to 0");
     assert!(coarseness <= 1.0, "Coarseness should be less than or equal to 1");
+    assert!(image.width() > 0 && image.height() > 0, "Image must not be empty");
+
+    let width = image.width();
+    let height = image.height();
+    let (span_x, span_y) = tile_spans(width, height);
+
+    // every pixel but the first corner gets overwritten
+    let edge_mode = image.edge_mode();
+    let mut lattice = Image::from_color(span_x, span_y, *image.get(0, 0));
+
+    run(&mut lattice, (span_x as i64, span_y as i64), variance, coarseness, seed);
+
+    *image = if width == span_x && height == span_y {
+        lattice
+    } else {
+        lattice.resize(width, height, SampleFilter::Bicubic)
+    }.with_edge_mode(edge_mode);
+}
+
+/// Power-of-two lattice sizes for a tileable image: the longer side rounds up, 
+/// and the shorter side keeps the nearest power-of-two aspect ratio
+fn tile_spans(width: usize, height: usize) -> (usize, usize) {
+    let longer = width.max(height).next_power_of_two();
+    let aspect = width.max(height) as f32 / width.min(height) as f32;
+    let shorter = (longer >> aspect.log2().round() as u32).max(1);
+
+    if width >= height {
+        (longer, shorter)
+    } else {
+        (shorter, longer)
+    }
+}
+
+/// Spans are the distances between the first pass's corners: one less than 
+/// the image's size normally, or the whole size when the corners wrap around
+fn run(image: &mut Image<GrayscaleColor>, spans: (i64, i64), variance: f32, coarseness: f32, seed: u64) {
+    let mut rng = seeded_rng(seed);
+    let span = spans.0.max(spans.1);
+    let mut partition_size = spans.0.min(spans.1);
+
+    // a lattice that isn't square starts out as a row or column of squares, 
+    // and all but the first corner still need heights
+    for x in (0..spans.0).step_by(partition_size as usize) {
+        for y in (0..spans.1).step_by(partition_size as usize) {
+            if x != 0 || y != 0 {
+                let val = *image.get(0, 0) + random_offset(&mut rng, variance);
+                image.set(x, y, val);
+            }
+        }
+    }
 
-    let size = image.width() as i64;
-    let mut partitions = 1;
     let mut running_variance = variance;
-    while partitions < size - 1 {
-        pass(image, partitions, running_variance);
-        
-        partitions *= 2;
+    while partition_size >= 2 {
+        let partitions = (spans.0 / partition_size, spans.1 / partition_size);
+        pass(image, &mut rng, partition_size, partitions, running_variance * variance_scale(span / partition_size, span));
+
+        partition_size /= 2;
         running_variance *= coarseness;
     }
 }
 
-fn pass(image: &mut Image<GrayscaleColor>, partitions: i64, variance: f32) {
-    let image_size = image.width() as i64;
-    let partition_size = (image_size - 1) / partitions;
-    let real_variance = variance * variance_scale(partitions, image_size);
+fn pass(image: &mut Image<GrayscaleColor>, rng: &mut SeededRng, partition_size: i64, partitions: (i64, i64), real_variance: f32) {
+    let image_width = image.width() as i64;
+    let image_height = image.height() as i64;
 
     // diamond
-    for partition_col in 0..partitions {
-        for partition_row in 0..partitions {
+    for partition_col in 0..partitions.0 {
+        for partition_row in 0..partitions.1 {
             let part = Partition::from(partition_col, partition_row, partition_size);
             
             let avg = average(part.corners().iter().map(|corner| image.get(corner.0, corner.1)));
-            let val = avg + random_offset(real_variance);
+            let val = avg + random_offset(rng, real_variance);
 
             image.set(part.mid_x, part.mid_y, val);
         }
@@ -62,14 +178,16 @@ fn pass(image: &mut Image<GrayscaleColor>, partitions: i64, variance: f32) {
 
     // square
     let mut visited: HashSet<(i64,i64)> = HashSet::new();
-    for partition_col in 0..partitions {
-        for partition_row in 0..partitions {
+    for partition_col in 0..partitions.0 {
+        for partition_row in 0..partitions.1 {
             let part = Partition::from(partition_col, partition_row, partition_size);
 
             part.sides().iter()
                 .for_each(|&coordinate| {
-                    if !visited.contains(&coordinate) {
-                        visited.insert(coordinate);
+                    // when tiling, sides on opposite edges are the same pixel
+                    let key = (coordinate.0.rem_euclid(image_width), coordinate.1.rem_euclid(image_height));
+                    if !visited.contains(&key) {
+                        visited.insert(key);
 
                         let avg = average([
                             *image.get(coordinate.0 - partition_size / 2, coordinate.1),
@@ -78,7 +196,7 @@ fn pass(image: &mut Image<GrayscaleColor>, partitions: i64, variance: f32) {
                             *image.get(coordinate.0, coordinate.1 + partition_size / 2)
                         ].iter());
 
-                        let val = avg + random_offset(real_variance);
+                        let val = avg + random_offset(rng, real_variance);
 
                         image.set(coordinate.0, coordinate.1, val);
                     }
@@ -87,14 +205,18 @@ fn pass(image: &mut Image<GrayscaleColor>, partitions: i64, variance: f32) {
     }
 }
 
-fn variance_scale(partitions: i64, image_size: i64) -> f32 {
+pub(crate) fn variance_scale(partitions: i64, span: i64) -> f32 {
     let pass_num = f32::log(partitions as f32, 2.0);           // how far along this pass is in the overall process
-    let total_passes = f32::log(image_size as f32 - 1.0, 2.0); // how many total passes will be made
+    let total_passes = f32::log(span as f32, 2.0);             // how many total passes will be made
     return 1.0 - (pass_num / total_passes);                    // reduce variance, the later in the process we are
 }
 
-fn random_offset(range: f32) -> f32 {
-    rand::thread_rng().gen_range(-1.0 * range / 2.0, range / 2.0)
+fn random_offset(rng: &mut SeededRng, range: f32) -> f32 {
+    // gen_range panics on an empty range
+    if range == 0.0 {
+        return 0.0;
+    }
+    rng.gen_range(-1.0 * range / 2.0, range / 2.0)
 }
 
 fn average<'a, I: Clone+Iterator<Item=&'a f32>>(nums: I) -> f32 {
@@ -153,3 +275,102 @@ fn corners(min_x: i64, max_x: i64, min_y: i64, max_y: i64) -> [(i64,i64); 4] {
         (max_x, max_y)
     ]
 }
+
+
+#[cfg(test)]
+mod tests {
+    use super::{apply_diamond_square, apply_tileable_diamond_square, generate_diamond_square, tile_spans};
+    use crate::image::Image;
+    use crate::generators::{DiamondSquareParams, Generator};
+
+    #[test]
+    fn test_any_size() {
+        let params = DiamondSquareParams { variance: 0.8, coarseness: 0.7, seed: 5, tileable: false };
+        let square = params.generate(33, 33);
+        let strip = params.generate(33, 20);
+        let odd = params.generate(7, 30);
+
+        assert_eq!((strip.width(), strip.height()), (33, 20));
+        assert_eq!((odd.width(), odd.height()), (7, 30));
+
+        // a smaller image is the corner of the square that covers it
+        for x in 0..33 {
+            for y in 0..20 {
+                assert_eq!(*strip.get(x, y), *square.get(x, y));
+            }
+        }
+        assert_eq!(*generate_diamond_square(33, 0.8, 0.7, 5).get(10, 10), *square.get(10, 10));
+    }
+
+    /**
+     * With no coarseness, only the first pass adds random offsets, and every 
+     * later point is exactly the average of its neighbors. Reading those 
+     * neighbors with wrap-around only gives back the stored values if the 
+     * lattice really does wrap, so this checks the tiling exactly.
+     */
+    fn assert_wrapped_averages(image: &Image<f32>, first_size: i64) {
+        let (width, height) = (image.width() as i64, image.height() as i64);
+        let wrapped = |x: i64, y: i64| *image.get(x.rem_euclid(width), y.rem_euclid(height));
+
+        let mut partition_size = first_size / 2;
+        while partition_size >= 2 {
+            let half = partition_size / 2;
+
+            for x in (0..width).step_by(half as usize) {
+                for y in (0..height).step_by(half as usize) {
+                    let neighbors = match ((x / half) % 2, (y / half) % 2) {
+                        (0, 0) => continue,
+                        (1, 1) => [(-half, -half), (half, -half), (-half, half), (half, half)],
+                        _ => [(-half, 0), (half, 0), (0, -half), (0, half)],
+                    };
+                    let average = neighbors.iter().map(|(dx, dy)| wrapped(x + dx, y + dy)).sum::<f32>() / 4.0;
+
+                    assert!((wrapped(x, y) - average).abs() < 0.00001, "({}, {}) at size {}", x, y, partition_size);
+                }
+            }
+
+            partition_size /= 2;
+        }
+    }
+
+    #[test]
+    fn test_tileable() {
+        let mut square = Image::from_color(32, 32, 0.5);
+        apply_tileable_diamond_square(&mut square, 1.0, 0.0, 3);
+        assert_wrapped_averages(&square, 32);
+
+        let mut wide = Image::from_color(64, 16, 0.5);
+        apply_tileable_diamond_square(&mut wide, 1.0, 0.0, 3);
+        assert_wrapped_averages(&wide, 16);
+
+        // the extra corners of a wide lattice get heights of their own
+        assert_ne!(*wide.get(0, 0), *wide.get(16, 0));
+        assert_ne!(*wide.get(16, 0), *wide.get(32, 0));
+    }
+
+    #[test]
+    fn test_tile_spans() {
+        assert_eq!(tile_spans(32, 32), (32, 32));
+        assert_eq!(tile_spans(20, 12), (32, 16));
+        assert_eq!(tile_spans(1920, 1080), (2048, 1024));
+        assert_eq!(tile_spans(100, 400), (128, 512));
+
+        let mut resized = Image::from_color(20, 12, 0.5);
+        apply_tileable_diamond_square(&mut resized, 1.0, 0.8, 3);
+        assert_eq!((resized.width(), resized.height()), (20, 12));
+    }
+
+    #[test]
+    #[should_panic(expected = "Image must not be empty")]
+    fn test_empty() {
+        let mut empty = Image::from_color(0, 8, 0.5);
+        apply_diamond_square(&mut empty, 0.5, 0.5, 1);
+    }
+
+    #[test]
+    #[should_panic(expected = "Image must not be empty")]
+    fn test_empty_tileable() {
+        let mut empty = Image::from_color(8, 0, 0.5);
+        apply_tileable_diamond_square(&mut empty, 0.5, 0.5, 1);
+    }
+}
@@ -0,0 +1,128 @@
+use serde::{Deserialize, Serialize};
+
+use crate::image::{GrayscaleColor, Image};
+use crate::noise::{add_fitted_noise, Fbm, Perlin};
+use crate::utils::random::hash_coords;
+use super::{Generator, add_periodic_perlin_noise};
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FbmParams {
+    pub grid_size: usize,
+    pub octaves: usize,
+    pub lacunarity: f32,
+    pub persistence: f32,
+    pub seed: u64,
+    /// Makes every octave periodic over the image, so the result tiles
+    #[serde(default)]
+    pub tileable: bool,
+}
+
+impl Generator for FbmParams {
+
+    fn generate(&self, width: usize, height: usize) -> Image<GrayscaleColor> {
+        let mut image = Image::from_color(width, height, 0.0);
+        if self.tileable {
+            apply_tileable_fbm(&mut image, self.grid_size, self.octaves, self.lacunarity, self.persistence, self.seed);
+        } else {
+            apply_fbm(&mut image, self.grid_size, self.octaves, self.lacunarity, self.persistence, self.seed);
+        }
+        image
+    }
+}
+
+/**
+ * Creates a new Image and runs apply_fbm on it.
+ */
+pub fn generate_fbm(size: usize, grid_size: usize, octaves: usize, lacunarity: f32, persistence: f32, seed: u64) -> Image<GrayscaleColor> {
+    let mut image = Image::from_color(size, size, 0.0);
+    apply_fbm(&mut image, grid_size, octaves, lacunarity, persistence, seed);
+    image
+}
+
+/**
+ * Replaces the image's contents with fractal Brownian motion: several octaves of 
+ * Perlin noise summed together (by noise::Fbm), then normalized into 0..1.
+ * 
+ * Grid size is the grid size of the first (coarsest) octave. Lacunarity is how 
+ * much finer the grid gets with each octave, persistence is how much weaker 
+ * each octave's contribution gets.
+ */
+pub fn apply_fbm(image: &mut Image<GrayscaleColor>, grid_size: usize, octaves: usize, lacunarity: f32, persistence: f32, seed: u64) {
+    fbm(image, grid_size, octaves, lacunarity, persistence, seed, false);
+}
+
+/**
+ * Same as apply_fbm, but every octave is periodic Perlin noise whose period 
+ * fits the image a whole number of times, so the result tiles seamlessly.
+ * Each octave's cell count is rounded to a whole number, so lacunarities that
+ * aren't whole numbers are only approximate; that rounding is also why the 
+ * octaves are summed here rather than by noise::Fbm, which scales positions by 
+ * exact powers of the lacunarity.
+ */
+pub fn apply_tileable_fbm(image: &mut Image<GrayscaleColor>, grid_size: usize, octaves: usize, lacunarity: f32, persistence: f32, seed: u64) {
+    fbm(image, grid_size, octaves, lacunarity, persistence, seed, true);
+}
+
+fn fbm(image: &mut Image<GrayscaleColor>, grid_size: usize, octaves: usize, lacunarity: f32, persistence: f32, seed: u64, tileable: bool) {
+    assert!(grid_size >= 2, "Grid size must be at least 2");
+    assert!(octaves >= 1, "There must be at least one octave");
+    assert!(lacunarity >= 1.0, "Lacunarity should be greater than or equal to 1");
+
+    for x in 0..image.width() as i64 {
+        for y in 0..image.height() as i64 {
+            image.set(x, y, 0.0);
+        }
+    }
+
+    let cells = (grid_size - 1) as f32;
+    if tileable {
+        add_tileable_octaves(image, cells, octaves, lacunarity, persistence, seed);
+    } else {
+        let noise = Fbm { noise: Perlin::new(seed), octaves, lacunarity, persistence };
+        add_fitted_noise(image, &noise, (cells, cells), 1.0);
+    }
+
+    image.normalize();
+}
+
+fn add_tileable_octaves(image: &mut Image<GrayscaleColor>, cells: f32, octaves: usize, lacunarity: f32, persistence: f32, seed: u64) {
+    let max_cells = image.width().min(image.height());
+
+    let mut frequency = 1.0;
+    let mut amplitude = 1.0;
+    for octave in 0..octaves {
+        // a cell can't be smaller than one pixel
+        let octave_cells = ((cells * frequency).round() as usize).min(max_cells).max(1);
+
+        // the seeds noise::Fbm gives its octaves; hashed rather than added, so
+        // neighboring seeds don't share octaves
+        let octave_seed = hash_coords(seed, &[octave as i64]);
+        add_periodic_perlin_noise(image, (octave_cells, octave_cells), amplitude, octave_seed);
+
+        frequency *= lacunarity;
+        amplitude *= persistence;
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::generate_fbm;
+
+    #[test]
+    fn test_normalized() {
+        let image = generate_fbm(64, 3, 5, 2.0, 0.5, 7);
+
+        let mut min = f32::INFINITY;
+        let mut max = f32::NEG_INFINITY;
+        for x in 0..64 {
+            for y in 0..64 {
+                min = min.min(*image.get(x, y));
+                max = max.max(*image.get(x, y));
+            }
+        }
+
+        assert_eq!(min, 0.0);
+        assert_eq!(max, 1.0);
+    }
+}
@@ -1,5 +1,10 @@
+use serde::{Deserialize, Serialize};
+
 use crate::image::{GrayscaleColor, Image};
+use super::Generator;
 
+#[derive(Debug, Copy, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
 pub enum HillShape {
     Constant,
     Linear,
@@ -7,25 +12,47 @@ pub enum HillShape {
     Smooth,
 }
 
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct HillParams {
+    pub location: (f32, f32),
+    pub shape: HillShape,
+    pub radius: f32,
+    pub height: f32,
+}
+
+impl Generator for HillParams {
+
+    fn generate(&self, width: usize, height: usize) -> Image<GrayscaleColor> {
+        let mut image = Image::from_color(width, height, 0.0);
+        add_hill(&mut image, self.location, self.shape, self.radius, self.height);
+        image
+    }
+}
+
 pub fn generate_hill(size: usize, hill_location: (f32, f32), hill_shape: HillShape, hill_radius: f32, hill_height: f32) -> Image<GrayscaleColor> {
     let mut image = Image::from_color(size, size, 0.0);
     add_hill(&mut image, hill_location, hill_shape, hill_radius, hill_height);
     return image;
 }
 
+/**
+ * Adds a hill to the image. Location is relative to the image's width and height,
+ * radius is relative to its width.
+ */
 pub fn add_hill(image: &mut Image<GrayscaleColor>, hill_location: (f32, f32), hill_shape: HillShape, hill_radius: f32, hill_height: f32) {
-    let size = image.width();
+    let width = image.width();
+    let height = image.height();
 
     assert!(hill_location.0 >= 0.0 && hill_location.0 < 1.0 && hill_location.1 >= 0.0 && hill_location.1 < 1.0, "Location x and y must both be >= 0 and < 1");
-    let hill_location = (hill_location.0 * size as f32, hill_location.1 * size as f32);
-    let hill_radius = hill_radius * size as f32;
+    let hill_location = (hill_location.0 * width as f32, hill_location.1 * height as f32);
+    let hill_radius = hill_radius * width as f32;
 
     let radius_squared = hill_radius * hill_radius;
 
     match hill_shape {
         HillShape::Constant => {
-            for x in 0..size {
-                for y in 0..size {
+            for x in 0..width {
+                for y in 0..height {
                     let distance_squared = calculate_distance_squared(x, y, hill_location);
 
                     if distance_squared < radius_squared {
@@ -37,8 +64,8 @@ pub fn add_hill(image: &mut Image<GrayscaleColor>, hill_location: (f32, f32), hi
         HillShape::Linear => {
             let radius = radius_squared.sqrt();
 
-            for x in 0..size {
-                for y in 0..size {
+            for x in 0..width {
+                for y in 0..height {
                     let distance_squared = calculate_distance_squared(x, y, hill_location);
 
                     if distance_squared < radius_squared {
@@ -50,8 +77,8 @@ pub fn add_hill(image: &mut Image<GrayscaleColor>, hill_location: (f32, f32), hi
             }
         },
         HillShape::Sphere => {
-            for x in 0..size {
-                for y in 0..size {
+            for x in 0..width {
+                for y in 0..height {
                     let distance_squared = calculate_distance_squared(x, y, hill_location);
 
                     if distance_squared < radius_squared {
@@ -63,8 +90,8 @@ pub fn add_hill(image: &mut Image<GrayscaleColor>, hill_location: (f32, f32), hi
         HillShape::Smooth => {
             let radius = radius_squared.sqrt();
 
-            for x in 0..size {
-                for y in 0..size {
+            for x in 0..width {
+                for y in 0..height {
                     let distance_squared = calculate_distance_squared(x, y, hill_location);
 
                     if distance_squared < radius_squared {
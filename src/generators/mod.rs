@@ -1,10 +1,39 @@
-
 mod bricks;
 mod diamond_square;
 mod perlin_noise;
 mod hill;
+mod fbm;
+mod simplex_noise;
+mod worley_noise;
+mod planet;
+mod chunked;
+
+pub use bricks::{BricksParams, generate_bricks, apply_bricks};
+pub use diamond_square::{DiamondSquareParams, generate_diamond_square, apply_diamond_square, apply_tileable_diamond_square};
+pub use perlin_noise::{PerlinParams, generate_perlin_noise, add_perlin_noise, add_periodic_perlin_noise};
+pub use hill::{HillParams, HillShape, generate_hill, add_hill};
+pub use fbm::{FbmParams, generate_fbm, apply_fbm, apply_tileable_fbm};
+pub use simplex_noise::{SimplexParams, generate_simplex_noise, add_simplex_noise};
+pub use worley_noise::{WorleyParams, WorleyChannels, WorleyChannel, DistanceMetric, generate_worley_channels, apply_worley_noise};
+pub use planet::{Planet, CubeFace};
+pub use chunked::{MidpointDisplacementParams, render_noise_chunk};
+
+use crate::blend::{blend_image, BlendMode};
+use crate::image::{GrayscaleColor, Image};
+
+/**
+ * Common interface over all of the generation algorithms, so that layers can be 
+ * stacked without knowing which algorithm produced each one. Each algorithm's
+ * parameter struct implements this.
+ */
+pub trait Generator {
+
+    /// Creates a new image of the given size containing only this generator's output
+    fn generate(&self, width: usize, height: usize) -> Image<GrayscaleColor>;
 
-pub use bricks::generate_bricks;
-pub use diamond_square::generate_diamond_square;
-pub use perlin_noise::{generate_perlin_noise, add_perlin_noise};
-pub use hill::{HillShape, generate_hill, add_hill};
+    /// Generates a layer the size of image and blends it into image
+    fn apply(&self, image: &mut Image<GrayscaleColor>, blend_mode: BlendMode) {
+        let layer = self.generate(image.width(), image.height());
+        blend_image(image, &layer, blend_mode, 1.0);
+    }
+}
@@ -1,165 +1,131 @@
+use std::f32::consts::FRAC_1_SQRT_2;
 
-use std::f32::consts::PI;
-use std::sync::{Arc, Mutex};
+use serde::{Deserialize, Serialize};
 
-extern crate rand;
-use rand::Rng;
+use crate::image::{GrayscaleColor, Image};
+use crate::noise::{add_fitted_noise, Perlin};
+use super::Generator;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PerlinParams {
+    pub grid_size: usize,
+    pub midpoint: f32,
+    pub scale: f32,
+    pub seed: u64,
+    /// When set, the noise repeats this many cells across and down the image
+    /// (grid_size is ignored), so the image tiles seamlessly
+    #[serde(default)]
+    pub period: Option<(usize, usize)>,
+}
 
-extern crate crossbeam;
+impl Generator for PerlinParams {
 
-use crate::image::{GrayscaleColor, Image};
-use crate::utils::vec2::Vec2;
+    fn generate(&self, width: usize, height: usize) -> Image<GrayscaleColor> {
+        let mut image = Image::from_color(width, height, self.midpoint);
+        match self.period {
+            Some(period) => add_periodic_perlin_noise(&mut image, period, self.scale, self.seed),
+            None => add_perlin_noise(&mut image, self.grid_size, self.scale, self.seed),
+        }
+        image
+    }
+}
 
 /**
  * Creates a new Image and runs apply_perlin_noise on it. 
  */
-pub fn generate_perlin_noise(size: usize, grid_size: usize, midpoint: f32, scale: f32) -> Image<GrayscaleColor> {
+pub fn generate_perlin_noise(size: usize, grid_size: usize, midpoint: f32, scale: f32, seed: u64) -> Image<GrayscaleColor> {
     let mut image = Image::from_color(size, size, midpoint);
-    add_perlin_noise(&mut image, grid_size, scale);
+    add_perlin_noise(&mut image, grid_size, scale, seed);
     return image;
 }
 
-pub fn add_perlin_noise(image: &mut Image<GrayscaleColor>, grid_size: usize, scale: f32) {
-    crossbeam::scope(move |scope| {
-        let cell_size_x = image.width() / (grid_size - 1);
-        let cell_size_y = image.height() / (grid_size - 1);
-
-        // initialize grid
-        let mut grid: Vec<Vec<Vec2>> = Vec::with_capacity(grid_size);
-        for i in 0..grid_size {
-            grid.push(Vec::with_capacity(grid_size));
-
-            for _ in 0..grid_size {
-                grid[i].push(generate_random_vector(2));
-            }
-        }
-
-        let arc_grid = Arc::new(grid);
-        let mutex_image: Arc<Mutex<&mut Image<GrayscaleColor>>> = Arc::new(Mutex::new(image));
-
-        for cell_x in 0..(grid_size - 1) {
-            for cell_y in 0..(grid_size - 1) {
-                let arc_grid_clone = Arc::clone(&arc_grid);
-                let mutex_image_clone = Arc::clone(&mutex_image);
-
-                scope.spawn(move |_| {
-                    perlin_cell(mutex_image_clone, &arc_grid_clone, scale, cell_size_x, cell_size_y, cell_x, cell_y);
-                });
-            }
-        }
-    }).unwrap();
-}
-
-fn perlin_cell(mutex_image: Arc<Mutex<&mut Image<GrayscaleColor>>>, grid: &Vec<Vec<Vec2>>, scale: f32, cell_size_x: usize, cell_size_y: usize, cell_x: usize, cell_y: usize) {
-    let mut buf = Vec::with_capacity(cell_size_x * cell_size_y);
-
-    for x in 0..cell_size_x {
-        for y in 0..cell_size_y {
-            let pixel_vec = Vec2 {
-                x: (x + (cell_x * cell_size_x)) as f32, 
-                y: (y + (cell_y * cell_size_y)) as f32
-            };
-
-            let corner_nodes = [
-                (cell_x, cell_y),
-                (cell_x + 1, cell_y),
-                (cell_x, cell_y + 1),
-                (cell_x + 1, cell_y + 1),
-            ];
-
-            let dots: Vec<f32> = corner_nodes.iter().map(|corner| {
-
-                // get distance of pixel from corner
-                let corner_vec = Vec2 {
-                    x: (corner.0 * cell_size_x) as f32, 
-                    y: (corner.1 * cell_size_y) as f32
-                };
-                let mut distance = &pixel_vec - &corner_vec;
-                distance.x /= cell_size_x as f32;
-                distance.y /= cell_size_y as f32;
-
-                // get random vec associated with corner
-                let corner_gradient_vec = &grid[corner.0][corner.1];
-
-                // compute the dot product and scale it to fit in 0..1
-                return distance.dot(&corner_gradient_vec) * scale;
-            }).collect();
-
-            let interpolation_1 = serp(dots[0], dots[1], x as f32 / cell_size_x as f32);
-            let interpolation_2 = serp(dots[2], dots[3], x as f32 / cell_size_x as f32);
-            let final_interpolation = serp(interpolation_1, interpolation_2, y as f32 / cell_size_y as f32);
-
-            //let image = mutex_image.lock().unwrap();
-            buf.push(final_interpolation);
-        }
-    }
-
-    let image: &mut Image<GrayscaleColor> = &mut mutex_image.lock().unwrap();
-    for i in 0..buf.len() {
-        let x = cell_x * cell_size_x + i / cell_size_y;
-        let y = cell_y * cell_size_y + i % cell_size_y;
-
-        image.add(x as i64, y as i64, buf[i]);
-    }
-}
-
-fn generate_random_vector(_dimensions: usize) -> Vec2 {
-    let mut vec = Vec2 {
-        x: rand::thread_rng().gen_range(-1.0, 1.0),
-        y: rand::thread_rng().gen_range(-1.0, 1.0),
-    };
-
-    while vec.len() > 1.0 {
-        vec.x = rand::thread_rng().gen_range(-1.0, 1.0);
-        vec.y = rand::thread_rng().gen_range(-1.0, 1.0);
-    }
+/**
+ * Adds a single octave of Perlin noise to the image, from noise::Perlin with 
+ * its lattice stretched so that grid_size points span each side of the image. 
+ * The noise falls in about -0.7..0.7 before being multiplied by scale, the 
+ * same range as the RNG-grid filler this replaced, so existing scales keep 
+ * their strength; only the easing between lattice points changed, from a 
+ * sine curve to smootherstep.
+ */
+pub fn add_perlin_noise(image: &mut Image<GrayscaleColor>, grid_size: usize, scale: f32, seed: u64) {
+    assert!(grid_size >= 2, "Grid size must be at least 2");
 
-    vec.normalize();
-    return vec;
+    let cells = (grid_size - 1) as f32;
+    add_fitted_noise(image, &Perlin::new(seed), (cells, cells), scale * UNSCALED);
 }
 
-fn lerp(a: f32, b: f32, t: f32) -> f32 {
-    (b - a) * t + a
+/**
+ * Adds a single octave of Perlin noise that repeats seamlessly across the 
+ * image. Period is the number of cells across and down the image; the 
+ * gradients wrap around after that many, so the last cell blends back into the
+ * first. Cells are sized to exactly fill the image, so any period works with 
+ * any image size, and the cells don't have to be square. Same range as 
+ * add_perlin_noise.
+ */
+pub fn add_periodic_perlin_noise(image: &mut Image<GrayscaleColor>, period: (usize, usize), scale: f32, seed: u64) {
+    let noise = Perlin::periodic(seed, period);
+    add_fitted_noise(image, &noise, (period.0 as f32, period.1 as f32), scale * UNSCALED);
 }
 
-fn serp(a: f32, b: f32, t: f32) -> f32 {
-    lerp(a, b, ((t - 0.5) * PI).sin() / 2.0 + 0.5)
-}
+// noise::Perlin is stretched out to -1..1; this undoes that, back to the 
+// sqrt(2)/2 peak of raw unit-gradient noise that scales were tuned against
+const UNSCALED: f32 = FRAC_1_SQRT_2;
 
-fn smoothstep(a: f32, b: f32, t: f32) -> f32 {
 
-    // Scale, bias and saturate x to 0..1 range
-    let range = b - a;
-    let portion = (t - a) / range;
-    let portion = clamp(portion, 0.0, 1.0);
+#[cfg(test)]
+mod tests {
+    use super::{add_periodic_perlin_noise, generate_perlin_noise, UNSCALED};
+    use crate::image::Image;
+    use crate::noise::{add_fitted_noise, Perlin};
 
-    // Evaluate polynomial
-    return portion * portion * (3.0 - 2.0 * portion);
-}
+    #[test]
+    fn test_same_seed_same_image() {
+        let image_1 = generate_perlin_noise(65, 9, 0.5, 0.5, 42);
+        let image_2 = generate_perlin_noise(65, 9, 0.5, 0.5, 42);
+        let image_3 = generate_perlin_noise(65, 9, 0.5, 0.5, 43);
 
-fn clamp(x: f32, min: f32, max: f32) -> f32 {
-    x.max(min).min(max)
-}
-
-/*
-enum Grid {
-    Dimension(Vec<Grid>),
-    Cell(Vector)
-}
+        let mut differs = false;
+        for x in 0..65 {
+            for y in 0..65 {
+                assert_eq!(image_1.get(x, y).to_bits(), image_2.get(x, y).to_bits());
+                differs = differs || image_1.get(x, y) != image_3.get(x, y);
+            }
+        }
+        assert!(differs);
+    }
 
-impl Grid {
-    fn new(size: usize, dimensions: usize) -> Self {
-        let mut grid: Self = Grid::Dimension(Vec::with_capacity(size));
+    #[test]
+    fn test_amplitude() {
+        // the same range as the old RNG-grid filler, so existing scales keep their strength
+        let image = generate_perlin_noise(128, 9, 0.0, 1.0, 3);
 
-        for dim in 2..dimensions {
-            grid = Grid::Dimension(Vec::with_capacity(size));
+        let mut peak: f32 = 0.0;
+        for x in 0..128 {
+            for y in 0..128 {
+                peak = peak.max(image.get(x, y).abs());
+            }
         }
-
-        return grid;
+        assert!(peak <= UNSCALED && peak > 0.4, "{}", peak);
     }
 
-    fn all(&mut self, cb: FnMut) {
-
+    #[test]
+    fn test_periodic_tiles() {
+        // two periods across and down, with 16-pixel cells so every position is exact
+        let mut repeated = Image::from_color(192, 128, 0.0);
+        add_fitted_noise(&mut repeated, &Perlin::periodic(9, (6, 4)), (12.0, 8.0), UNSCALED);
+
+        // a single period is exactly what add_periodic_perlin_noise fills the image with
+        let mut image = Image::from_color(96, 64, 0.0);
+        add_periodic_perlin_noise(&mut image, (6, 4), 1.0, 9);
+
+        for x in 0..96 {
+            for y in 0..64 {
+                let value = image.get(x, y).to_bits();
+                assert_eq!(value, repeated.get(x, y).to_bits(), "at ({}, {})", x, y);
+                assert_eq!(value, repeated.get(x + 96, y).to_bits(), "at ({}, {})", x, y);
+                assert_eq!(value, repeated.get(x, y + 64).to_bits(), "at ({}, {})", x, y);
+                assert_eq!(value, repeated.get(x + 96, y + 64).to_bits(), "at ({}, {})", x, y);
+            }
+        }
     }
 }
-*/
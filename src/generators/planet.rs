@@ -0,0 +1,187 @@
+use std::f32::consts::PI;
+
+use crate::image::{GrayscaleColor, Image};
+use crate::noise::NoiseFn3;
+use crate::utils::matrix::Matrix;
+use crate::utils::vec3::Vec3;
+
+/// The six faces of a cube map, in the usual +X, -X, +Y, -Y, +Z, -Z order
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum CubeFace {
+    PositiveX,
+    NegativeX,
+    PositiveY,
+    NegativeY,
+    PositiveZ,
+    NegativeZ,
+}
+
+impl CubeFace {
+
+    pub const ALL: [CubeFace; 6] = [
+        CubeFace::PositiveX,
+        CubeFace::NegativeX,
+        CubeFace::PositiveY,
+        CubeFace::NegativeY,
+        CubeFace::PositiveZ,
+        CubeFace::NegativeZ,
+    ];
+
+    /**
+     * The point on this face of a cube from -1 to 1 at u (rightwards) and v
+     * (downwards), both also from -1 to 1. Faces are oriented the same way as
+     * OpenGL's cube maps, with +Y up.
+     */
+    pub fn direction(&self, u: f32, v: f32) -> Vec3 {
+        match self {
+            CubeFace::PositiveX => Vec3 { x: 1.0, y: -v, z: -u },
+            CubeFace::NegativeX => Vec3 { x: -1.0, y: -v, z: u },
+            CubeFace::PositiveY => Vec3 { x: u, y: 1.0, z: v },
+            CubeFace::NegativeY => Vec3 { x: u, y: -1.0, z: -v },
+            CubeFace::PositiveZ => Vec3 { x: u, y: -v, z: 1.0 },
+            CubeFace::NegativeZ => Vec3 { x: -u, y: -v, z: -1.0 },
+        }
+    }
+}
+
+/**
+ * Heightmaps for a spherical planet, made by sampling 3D noise on the surface
+ * of a sphere. Because the noise is never stretched over a flat image, there
+ * are no seams and no pinching at the poles.
+ *
+ * Radius is the size of the sphere in noise units, so larger planets have more
+ * (and relatively smaller) features. Rotation turns the sphere before it's
+ * sampled, which tilts the poles or picks a different part of the noise.
+ * Noise values from -1 to 1 are mapped into 0..1 heights.
+ */
+#[derive(Debug, Clone, PartialEq)]
+pub struct Planet<N: NoiseFn3> {
+    pub noise: N,
+    pub radius: f32,
+    pub rotation: Matrix,
+}
+
+impl<N: NoiseFn3> Planet<N> {
+
+    pub fn new(noise: N, radius: f32) -> Self {
+        Planet { noise, radius, rotation: Matrix::new() }
+    }
+
+    /// Height of the surface in the given direction from the planet's center
+    pub fn height(&self, direction: &Vec3) -> GrayscaleColor {
+        let point = &direction.normalized().transformed(&self.rotation) * self.radius;
+        self.noise.sample(point) * 0.5 + 0.5
+    }
+
+    /**
+     * Creates an equirectangular map: longitude runs across the image and
+     * latitude down it, from the north pole at the top to the south pole at
+     * the bottom. The left and right edges meet seamlessly. Width is usually
+     * twice the height, so that pixels are square at the equator.
+     */
+    pub fn equirectangular(&self, width: usize, height: usize) -> Image<GrayscaleColor> {
+        let mut image = Image::from_color(width, height, 0.0);
+
+        for x in 0..width {
+            for y in 0..height {
+                let longitude = (x as f32 + 0.5) / width as f32 * 2.0 * PI;
+                let latitude = PI / 2.0 - (y as f32 + 0.5) / height as f32 * PI;
+
+                image.set(x as i64, y as i64, self.height(&Vec3::from_angles(longitude, latitude)));
+            }
+        }
+
+        image
+    }
+
+    /**
+     * Creates a single size x size cube map face. The outermost pixels sit
+     * exactly on the cube's edges, so pixels along the shared edge of two
+     * neighboring faces have identical values, which keeps the faces seamless
+     * when they're used as heightmaps for a cube-sphere mesh.
+     */
+    pub fn cube_face(&self, face: CubeFace, size: usize) -> Image<GrayscaleColor> {
+        assert!(size >= 2, "Cube faces must be at least 2x2");
+
+        let mut image = Image::from_color(size, size, 0.0);
+        let to_cube = |pixel: usize| pixel as f32 / (size - 1) as f32 * 2.0 - 1.0;
+
+        for x in 0..size {
+            for y in 0..size {
+                image.set(x as i64, y as i64, self.height(&face.direction(to_cube(x), to_cube(y))));
+            }
+        }
+
+        image
+    }
+
+    /// Creates all six cube map faces, in the same order as CubeFace::ALL
+    pub fn cube_map(&self, size: usize) -> Vec<Image<GrayscaleColor>> {
+        CubeFace::ALL.iter().map(|face| self.cube_face(*face, size)).collect()
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use std::f32::consts::PI;
+
+    use super::{CubeFace, Planet};
+    use crate::noise::{Fbm, Perlin3};
+    use crate::utils::vec3::Vec3;
+
+    fn planet() -> Planet<Fbm<Perlin3>> {
+        Planet::new(Fbm::new(Perlin3::new(6), 4), 2.0)
+    }
+
+    #[test]
+    fn test_cube_edges_line_up() {
+        let faces = planet().cube_map(16);
+        let face = |face: CubeFace| &faces[CubeFace::ALL.iter().position(|f| *f == face).unwrap()];
+
+        for i in 0..16 {
+            // +Z's right edge is +X's left edge, and +Y's bottom edge is +Z's top edge
+            assert!((face(CubeFace::PositiveZ).get(15, i) - face(CubeFace::PositiveX).get(0, i)).abs() < 0.0001);
+            assert!((face(CubeFace::PositiveY).get(i, 15) - face(CubeFace::PositiveZ).get(i, 0)).abs() < 0.0001);
+        }
+    }
+
+    #[test]
+    fn test_equirectangular_wraps() {
+        let planet = planet();
+        let map = planet.equirectangular(64, 32);
+        let pixel_angle = 2.0 * PI / 64.0;
+
+        for y in 0..32 {
+            let latitude = PI / 2.0 - (y as f32 + 0.5) / 32.0 * PI;
+
+            // longitudes 0 and 2π are the same meridian
+            let start = planet.height(&Vec3::from_angles(0.0, latitude));
+            let end = planet.height(&Vec3::from_angles(2.0 * PI, latitude));
+            assert!((start - end).abs() < 0.0001, "row {}: {} vs {}", y, start, end);
+
+            // so the last column sits just west of the first, half a pixel before longitude 0
+            let west = planet.height(&Vec3::from_angles(-pixel_angle / 2.0, latitude));
+            assert!((map.get(63, y) - west).abs() < 0.0001, "row {}: {} vs {}", y, map.get(63, y), west);
+        }
+    }
+
+    #[test]
+    fn test_equirectangular_poles() {
+        let planet = planet();
+        let north = planet.height(&Vec3 { x: 0.0, y: 1.0, z: 0.0 });
+
+        // the top row circles the pole more tightly as the map gets taller
+        let mut previous = f32::INFINITY;
+        for &height in [16, 64, 256].iter() {
+            let map = planet.equirectangular(height * 2, height);
+            let furthest = (0..height as i64 * 2).map(|x| (map.get(x, 0) - north).abs()).fold(0.0, f32::max);
+
+            assert!(furthest < previous, "{} rows: {} vs {}", height, furthest, previous);
+            previous = furthest;
+        }
+
+        // at 256 rows, the top row is only π/512 radians from the pole
+        assert!(previous < 0.01, "{}", previous);
+    }
+}
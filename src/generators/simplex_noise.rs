@@ -0,0 +1,40 @@
+use serde::{Deserialize, Serialize};
+
+use crate::image::{GrayscaleColor, Image};
+use crate::noise::{add_noise, Simplex};
+use super::Generator;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SimplexParams {
+    pub feature_size: f32,
+    pub midpoint: f32,
+    pub scale: f32,
+    pub seed: u64,
+}
+
+impl Generator for SimplexParams {
+
+    fn generate(&self, width: usize, height: usize) -> Image<GrayscaleColor> {
+        generate_simplex_noise(width, height, self.feature_size, self.midpoint, self.scale, self.seed)
+    }
+}
+
+/**
+ * Creates a new Image and runs add_simplex_noise on it.
+ */
+pub fn generate_simplex_noise(width: usize, height: usize, feature_size: f32, midpoint: f32, scale: f32, seed: u64) -> Image<GrayscaleColor> {
+    let mut image = Image::from_color(width, height, midpoint);
+    add_simplex_noise(&mut image, feature_size, scale, seed);
+    image
+}
+
+/**
+ * Adds OpenSimplex2 noise to the image.
+ *
+ * Feature size is the rough width, in pixels, of a single bump; unlike Perlin
+ * noise there's no grid to line up with the image, so any image size works. The
+ * noise falls in -1..1 before being multiplied by scale.
+ */
+pub fn add_simplex_noise(image: &mut Image<GrayscaleColor>, feature_size: f32, scale: f32, seed: u64) {
+    add_noise(image, &Simplex::new(seed), feature_size, scale);
+}
@@ -0,0 +1,129 @@
+use serde::{Deserialize, Serialize};
+
+use crate::image::{GrayscaleColor, Image};
+use crate::noise::worley::{sample_cells, WorleySample};
+use crate::utils::vec2::Vec2;
+use super::Generator;
+
+pub use crate::noise::{DistanceMetric, WorleyChannel};
+
+/**
+ * Cells x and y are the number of cells (each containing one feature point) 
+ * across the image. Jitter is how far, from 0 to 1, each feature point may 
+ * stray from the center of its cell. When tileable is set, cells on opposite 
+ * edges of the image share feature points so the result repeats seamlessly.
+ */
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct WorleyParams {
+    pub cells_x: usize,
+    pub cells_y: usize,
+    pub jitter: f32,
+    pub distance: DistanceMetric,
+    pub channel: WorleyChannel,
+    pub tileable: bool,
+    pub seed: u64,
+}
+
+impl Generator for WorleyParams {
+
+    fn generate(&self, width: usize, height: usize) -> Image<GrayscaleColor> {
+        let mut image = Image::from_color(width, height, 0.0);
+        apply_worley_noise(&mut image, self);
+        image
+    }
+}
+
+pub struct WorleyChannels {
+    pub f1: Image<GrayscaleColor>,
+    pub f2: Image<GrayscaleColor>,
+    pub f2_minus_f1: Image<GrayscaleColor>,
+    pub cell_id: Image<GrayscaleColor>,
+}
+
+/**
+ * Computes all Worley noise channels in a single pass, ignoring params.channel. 
+ * Distances are measured in units of cell size and clamped to 0..1.
+ */
+pub fn generate_worley_channels(width: usize, height: usize, params: &WorleyParams) -> WorleyChannels {
+    let mut channels = WorleyChannels {
+        f1: Image::from_color(width, height, 0.0),
+        f2: Image::from_color(width, height, 0.0),
+        f2_minus_f1: Image::from_color(width, height, 0.0),
+        cell_id: Image::from_color(width, height, 0.0),
+    };
+
+    for x in 0..width as i64 {
+        for y in 0..height as i64 {
+            let sample = sample_worley(params, width, height, x as f32 + 0.5, y as f32 + 0.5);
+
+            channels.f1.set(x, y, sample.value(WorleyChannel::F1).min(1.0));
+            channels.f2.set(x, y, sample.value(WorleyChannel::F2).min(1.0));
+            channels.f2_minus_f1.set(x, y, sample.value(WorleyChannel::F2MinusF1).min(1.0));
+            channels.cell_id.set(x, y, sample.value(WorleyChannel::CellId));
+        }
+    }
+
+    channels
+}
+
+/**
+ * Replaces the image's contents with the Worley noise channel selected in params.
+ */
+pub fn apply_worley_noise(image: &mut Image<GrayscaleColor>, params: &WorleyParams) {
+    let width = image.width();
+    let height = image.height();
+
+    for x in 0..width as i64 {
+        for y in 0..height as i64 {
+            let sample = sample_worley(params, width, height, x as f32 + 0.5, y as f32 + 0.5);
+
+            image.set(x, y, sample.value(params.channel).min(1.0));
+        }
+    }
+}
+
+fn sample_worley(params: &WorleyParams, width: usize, height: usize, x: f32, y: f32) -> WorleySample {
+    assert!(params.cells_x > 0 && params.cells_y > 0, "There must be at least one cell in each direction");
+
+    // position in units of cells
+    let position = Vec2 {
+        x: x / (width as f32 / params.cells_x as f32),
+        y: y / (height as f32 / params.cells_y as f32),
+    };
+    let period = if params.tileable { Some((params.cells_x, params.cells_y)) } else { None };
+
+    sample_cells(params.seed, params.jitter, params.distance, position, period)
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::{sample_worley, DistanceMetric, WorleyChannel, WorleyParams};
+
+    #[test]
+    fn test_tileable() {
+        let params = WorleyParams {
+            cells_x: 5,
+            cells_y: 3,
+            jitter: 1.0,
+            distance: DistanceMetric::Euclidean,
+            channel: WorleyChannel::F1,
+            tileable: true,
+            seed: 11,
+        };
+
+        for x in 0..100 {
+            let a = sample_worley(&params, 100, 60, x as f32 + 0.5, 0.5);
+            let b = sample_worley(&params, 100, 60, x as f32 + 0.5, 60.5);
+            assert!((a.f1 - b.f1).abs() < 0.0001);
+            assert_eq!(a.cell_id, b.cell_id);
+        }
+
+        for y in 0..60 {
+            let a = sample_worley(&params, 100, 60, 0.5, y as f32 + 0.5);
+            let b = sample_worley(&params, 100, 60, 100.5, y as f32 + 0.5);
+            assert!((a.f1 - b.f1).abs() < 0.0001);
+            assert_eq!(a.cell_id, b.cell_id);
+        }
+    }
+}
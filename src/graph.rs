@@ -0,0 +1,431 @@
+use std::collections::{BTreeMap, HashMap};
+use std::{fmt, fs, io, path::Path, str::FromStr};
+
+use serde::{Deserialize, Serialize};
+
+use crate::blend::{blend_image, blend_image_masked, BlendMode};
+use crate::color::Rgb;
+use crate::filters::{
+    apply_domain_warp, apply_gaussian_blur, apply_hydraulic_erosion, apply_thermal_erosion, generate_normal_map,
+    DomainWarpParams, HydraulicErosionParams, NormalMapFormat, NormalMapMethod, ThermalErosionParams
+};
+use crate::image::{GrayscaleColor, Image};
+use crate::pipeline::LayerGenerator;
+
+pub type NodeId = String;
+
+/**
+ * A single step in a Graph. Generator nodes create an image from nothing,
+ * filter nodes transform one input and combiner nodes merge several.
+ */
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "kebab-case")]
+pub enum Node {
+    Generator(LayerGenerator),
+    Filter {
+        input: NodeId,
+        #[serde(flatten)]
+        filter: Filter,
+    },
+    Blend {
+        base: NodeId,
+        layer: NodeId,
+        mode: BlendMode,
+        #[serde(default = "default_opacity")]
+        opacity: f32,
+        /// Limits the blend to where this node's output is bright
+        #[serde(default)]
+        mask: Option<NodeId>,
+    },
+    /// Multiplies input by mask, so it only shows through where the mask is bright
+    Mask {
+        input: NodeId,
+        mask: NodeId,
+    },
+    /// Offsets where input is read from by two displacement nodes, one per axis
+    Warp {
+        input: NodeId,
+        displacement_x: NodeId,
+        displacement_y: NodeId,
+        #[serde(flatten)]
+        params: DomainWarpParams,
+    },
+    Max {
+        inputs: Vec<NodeId>,
+    },
+    Min {
+        inputs: Vec<NodeId>,
+    },
+}
+
+fn default_opacity() -> f32 {
+    1.0
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "filter", rename_all = "kebab-case")]
+pub enum Filter {
+    Blur {
+        radius: f32,
+    },
+    HydraulicErosion(HydraulicErosionParams),
+    ThermalErosion(ThermalErosionParams),
+    Normalize,
+    /// Produces a color image, so it can only feed other nodes that accept color
+    NormalMap {
+        strength: f32,
+        method: NormalMapMethod,
+        format: NormalMapFormat,
+    },
+}
+
+impl Node {
+
+    /// IDs of the nodes this one reads from
+    pub fn inputs(&self) -> Vec<&NodeId> {
+        match self {
+            Node::Generator(_) => vec![],
+            Node::Filter { input, .. } => vec![input],
+            Node::Blend { base, layer, mask, .. } => vec![base, layer].into_iter().chain(mask).collect(),
+            Node::Mask { input, mask } => vec![input, mask],
+            Node::Warp { input, displacement_x, displacement_y, .. } => vec![input, displacement_x, displacement_y],
+            Node::Max { inputs } | Node::Min { inputs } => inputs.iter().collect(),
+        }
+    }
+}
+
+/// The output of a node
+#[derive(Clone)]
+pub enum NodeImage {
+    Grayscale(Image<GrayscaleColor>),
+    Color(Image<Rgb>),
+}
+
+impl NodeImage {
+
+    pub fn to_rgb(&self) -> Image<Rgb> {
+        match self {
+            NodeImage::Grayscale(image) => image.to_rgb(),
+            NodeImage::Color(image) => image.clone(),
+        }
+    }
+}
+
+/**
+ * A directed acyclic graph of image-producing nodes, keyed by ID.
+ *
+ * Evaluated nodes are cached, and replacing a node only throws away the cached
+ * output of that node and the nodes downstream of it, so tweaking one parameter
+ * doesn't mean re-running everything. Graphs can be saved and loaded as TOML:
+ *
+ * ```toml
+ * width = 512
+ * height = 512
+ *
+ * [nodes.noise]
+ * type = "generator"
+ * generator = "fbm"
+ * grid_size = 3
+ * octaves = 6
+ * lacunarity = 2.0
+ * persistence = 0.5
+ * seed = 1
+ *
+ * [nodes.eroded]
+ * type = "filter"
+ * input = "noise"
+ * filter = "thermal-erosion"
+ * iterations = 20
+ * ```
+ */
+#[derive(Serialize, Deserialize)]
+pub struct Graph {
+    width: usize,
+    height: usize,
+    nodes: BTreeMap<NodeId, Node>,
+    #[serde(skip)]
+    cache: HashMap<NodeId, NodeImage>,
+}
+
+impl Graph {
+
+    pub fn new(width: usize, height: usize) -> Self {
+        Graph {
+            width,
+            height,
+            nodes: BTreeMap::new(),
+            cache: HashMap::new(),
+        }
+    }
+
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, GraphError> {
+        fs::read_to_string(path)?.parse()
+    }
+
+    pub fn to_toml(&self) -> String {
+        toml::to_string(self).unwrap()
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    pub fn node(&self, id: &str) -> Option<&Node> {
+        self.nodes.get(id)
+    }
+
+    pub fn node_ids(&self) -> impl Iterator<Item=&NodeId> {
+        self.nodes.keys()
+    }
+
+    /// Adds a node, or replaces the existing node with the same ID
+    pub fn set_node<I: Into<NodeId>>(&mut self, id: I, node: Node) {
+        let id = id.into();
+        self.invalidate(&id);
+        self.nodes.insert(id, node);
+    }
+
+    pub fn remove_node(&mut self, id: &str) -> Option<Node> {
+        self.invalidate(id);
+        self.nodes.remove(id)
+    }
+
+    /// Whether the node's output is currently cached
+    pub fn is_cached(&self, id: &str) -> bool {
+        self.cache.contains_key(id)
+    }
+
+    /// Evaluates a node, and any of its inputs that aren't already cached
+    pub fn evaluate(&mut self, id: &str) -> Result<&NodeImage, GraphError> {
+        self.ensure_evaluated(id, &mut Vec::new())?;
+        Ok(&self.cache[id])
+    }
+
+    /// Drops the cached output of a node and everything downstream of it
+    fn invalidate(&mut self, id: &str) {
+        if self.cache.remove(id).is_none() {
+            // nothing downstream can be cached if this wasn't
+            return;
+        }
+
+        let dependents: Vec<NodeId> = self.nodes.iter()
+            .filter(|(_, node)| node.inputs().iter().any(|input| input.as_str() == id))
+            .map(|(dependent, _)| dependent.clone())
+            .collect();
+
+        for dependent in dependents {
+            self.invalidate(&dependent);
+        }
+    }
+
+    fn ensure_evaluated(&mut self, id: &str, stack: &mut Vec<NodeId>) -> Result<(), GraphError> {
+        if self.cache.contains_key(id) {
+            return Ok(());
+        }
+        if stack.iter().any(|visiting| visiting == id) {
+            return Err(GraphError::Cycle(id.to_string()));
+        }
+
+        let node = self.nodes.get(id).ok_or_else(|| GraphError::MissingNode(id.to_string()))?.clone();
+
+        stack.push(id.to_string());
+        for input in node.inputs() {
+            self.ensure_evaluated(input, stack)?;
+        }
+        stack.pop();
+
+        let output = self.compute(&node)?;
+        self.cache.insert(id.to_string(), output);
+
+        Ok(())
+    }
+
+    /// Runs a single node; all of its inputs must already be cached
+    fn compute(&self, node: &Node) -> Result<NodeImage, GraphError> {
+        let output = match node {
+            Node::Generator(generator) => {
+                generator.as_generator().generate(self.width, self.height)
+            },
+            Node::Filter { input, filter } => {
+                let mut image = self.grayscale_input(input)?.clone();
+
+                match filter {
+                    Filter::Blur { radius } => apply_gaussian_blur(&mut image, *radius),
+                    Filter::HydraulicErosion(params) => apply_hydraulic_erosion(&mut image, params),
+                    Filter::ThermalErosion(params) => apply_thermal_erosion(&mut image, params),
+                    Filter::Normalize => image.normalize(),
+                    Filter::NormalMap { strength, method, format } => {
+                        return Ok(NodeImage::Color(generate_normal_map(&image, *strength, *method, *format)));
+                    },
+                }
+
+                image
+            },
+            Node::Blend { base, layer, mode, opacity, mask } => {
+                let mut image = self.grayscale_input(base)?.clone();
+                match mask {
+                    Some(mask) => blend_image_masked(&mut image, self.grayscale_input(layer)?, *mode, *opacity, self.grayscale_input(mask)?),
+                    None => blend_image(&mut image, self.grayscale_input(layer)?, *mode, *opacity),
+                }
+                image
+            },
+            Node::Mask { input, mask } => {
+                let mut image = self.grayscale_input(input)?.clone();
+                blend_image(&mut image, self.grayscale_input(mask)?, BlendMode::Multiply, 1.0);
+                image
+            },
+            Node::Warp { input, displacement_x, displacement_y, params } => {
+                let mut image = self.grayscale_input(input)?.clone();
+                apply_domain_warp(&mut image, self.grayscale_input(displacement_x)?, self.grayscale_input(displacement_y)?, params);
+                image
+            },
+            Node::Max { inputs } => self.combine(inputs, BlendMode::Max)?,
+            Node::Min { inputs } => self.combine(inputs, BlendMode::Min)?,
+        };
+
+        Ok(NodeImage::Grayscale(output))
+    }
+
+    fn combine(&self, inputs: &[NodeId], blend_mode: BlendMode) -> Result<Image<GrayscaleColor>, GraphError> {
+        let (first, rest) = inputs.split_first().ok_or(GraphError::NoInputs)?;
+
+        let mut image = self.grayscale_input(first)?.clone();
+        for input in rest {
+            blend_image(&mut image, self.grayscale_input(input)?, blend_mode, 1.0);
+        }
+
+        Ok(image)
+    }
+
+    fn grayscale_input(&self, id: &str) -> Result<&Image<GrayscaleColor>, GraphError> {
+        match &self.cache[id] {
+            NodeImage::Grayscale(image) => Ok(image),
+            NodeImage::Color(_) => Err(GraphError::NotGrayscale(id.to_string())),
+        }
+    }
+}
+
+impl FromStr for Graph {
+    type Err = GraphError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ok(toml::from_str(s)?)
+    }
+}
+
+#[derive(Debug)]
+pub enum GraphError {
+    /// A node refers to an ID that isn't in the graph
+    MissingNode(NodeId),
+    /// The node depends, directly or indirectly, on itself
+    Cycle(NodeId),
+    /// A color node was used as the input to a node that needs grayscale
+    NotGrayscale(NodeId),
+    /// A combiner node was given no inputs
+    NoInputs,
+    Io(io::Error),
+    Parse(toml::de::Error),
+}
+
+impl fmt::Display for GraphError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            GraphError::MissingNode(id) => write!(f, "no node with ID '{}'", id),
+            GraphError::Cycle(id) => write!(f, "node '{}' depends on itself", id),
+            GraphError::NotGrayscale(id) => write!(f, "node '{}' produces a color image where grayscale is needed", id),
+            GraphError::NoInputs => write!(f, "combiner node has no inputs"),
+            GraphError::Io(error) => write!(f, "couldn't read graph: {}", error),
+            GraphError::Parse(error) => write!(f, "invalid graph: {}", error),
+        }
+    }
+}
+
+impl std::error::Error for GraphError {}
+
+impl From<io::Error> for GraphError {
+    fn from(error: io::Error) -> Self {
+        GraphError::Io(error)
+    }
+}
+
+impl From<toml::de::Error> for GraphError {
+    fn from(error: toml::de::Error) -> Self {
+        GraphError::Parse(error)
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::{Filter, Graph, GraphError, Node, NodeImage};
+    use crate::blend::BlendMode;
+    use crate::filters::{DomainWarpParams, NormalMapFormat, NormalMapMethod};
+    use crate::generators::{HillParams, HillShape, PerlinParams};
+    use crate::pipeline::LayerGenerator;
+
+    fn hill() -> Node {
+        Node::Generator(LayerGenerator::Hill(HillParams { location: (0.5, 0.5), shape: HillShape::Linear, radius: 0.4, height: 1.0 }))
+    }
+
+    fn noise(seed: u64) -> Node {
+        Node::Generator(LayerGenerator::Perlin(PerlinParams { grid_size: 5, midpoint: 0.0, scale: 0.1, seed, period: None }))
+    }
+
+    #[test]
+    fn test_only_dirty_nodes_reevaluated() {
+        let mut graph = Graph::new(32, 32);
+        graph.set_node("hill", hill());
+        graph.set_node("noise", noise(1));
+        graph.set_node("sum", Node::Blend { base: "hill".to_string(), layer: "noise".to_string(), mode: BlendMode::Add, opacity: 1.0, mask: None });
+        graph.set_node("smooth", Node::Filter { input: "sum".to_string(), filter: Filter::Blur { radius: 1.0 } });
+
+        graph.evaluate("smooth").unwrap();
+        assert!(graph.is_cached("hill") && graph.is_cached("noise") && graph.is_cached("sum"));
+
+        graph.set_node("noise", noise(2));
+        assert!(graph.is_cached("hill"));
+        assert!(!graph.is_cached("noise") && !graph.is_cached("sum") && !graph.is_cached("smooth"));
+
+        match graph.evaluate("smooth").unwrap() {
+            NodeImage::Grayscale(image) => assert_eq!(image.width(), 32),
+            NodeImage::Color(_) => panic!("expected grayscale"),
+        }
+    }
+
+    #[test]
+    fn test_errors() {
+        let mut graph = Graph::new(8, 8);
+        graph.set_node("a", Node::Max { inputs: vec!["b".to_string()] });
+        graph.set_node("b", Node::Mask { input: "a".to_string(), mask: "a".to_string() });
+        graph.set_node("c", Node::Min { inputs: vec!["missing".to_string()] });
+
+        assert!(matches!(graph.evaluate("a"), Err(GraphError::Cycle(_))));
+        assert!(matches!(graph.evaluate("c"), Err(GraphError::MissingNode(_))));
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let mut graph = Graph::new(16, 16);
+        graph.set_node("hill", hill());
+        graph.set_node("normals", Node::Filter {
+            input: "hill".to_string(),
+            filter: Filter::NormalMap { strength: 2.0, method: NormalMapMethod::Sobel, format: NormalMapFormat::OpenGl },
+        });
+        graph.set_node("noise", noise(3));
+        graph.set_node("warped", Node::Warp {
+            input: "hill".to_string(),
+            displacement_x: "noise".to_string(),
+            displacement_y: "noise".to_string(),
+            params: DomainWarpParams { strength: 4.0, ..DomainWarpParams::default() },
+        });
+
+        let loaded: Graph = graph.to_toml().parse().unwrap();
+        assert_eq!(loaded.node("hill"), graph.node("hill"));
+        assert_eq!(loaded.node("normals"), graph.node("normals"));
+        assert_eq!(loaded.node("warped"), graph.node("warped"));
+    }
+}
@@ -1,20 +1,43 @@
 
 use std::{iter::FromIterator, ops::{Add, Mul}};
 
+use crate::color::Rgb;
+
 pub fn float_to_u8(f: f32) -> u8 {
     (f * 255.0) as u8
 }
 
 pub type IntColor = (u8, u8, u8);
 
-pub type FloatColor = (f32, f32, f32);
+pub type FloatColor = Rgb;
 
 pub type GrayscaleColor = f32;
 
+/**
+ * What happens when a pixel outside of an image is read or written. Wrapping 
+ * is the default, since it makes tileable textures fall out naturally; 
+ * terrain usually wants Clamp instead.
+ */
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum EdgeMode<P> {
+    /// Coordinates wrap around to the opposite side
+    Wrap,
+    /// Coordinates are moved to the nearest edge pixel
+    Clamp,
+    /// The image is reflected at its edges, repeating the edge pixel
+    Mirror,
+    /// Reads outside the image return this color, and writes are ignored
+    Constant(P),
+    /// Any access outside the image is a bug
+    Panic,
+}
+
+#[derive(Clone)]
 pub struct Image<P: Copy + Add<Output=P> + Mul<Output=P>> {
     width: usize,
     height: usize,
-    pixels: Vec<P>
+    pixels: Vec<P>,
+    edge_mode: EdgeMode<P>
 }
 
 impl<P: Copy + Add<Output=P> + Mul<Output=P>> Image<P> {
@@ -23,7 +46,8 @@ impl<P: Copy + Add<Output=P> + Mul<Output=P>> Image<P> {
         Image { 
             width, 
             height, 
-            pixels: Vec::with_capacity(width * height) 
+            pixels: Vec::with_capacity(width * height),
+            edge_mode: EdgeMode::Wrap
         }
     }
 
@@ -31,10 +55,27 @@ impl<P: Copy + Add<Output=P> + Mul<Output=P>> Image<P> {
         Image {
             width,
             height,
-            pixels: Vec::from_iter((0..(width*height)).map(|_| color))
+            pixels: Vec::from_iter((0..(width*height)).map(|_| color)),
+            edge_mode: EdgeMode::Wrap
         }
     }
 
+    /// Wraps pixels that are already laid out row by row
+    pub(crate) fn from_pixels(width: usize, height: usize, pixels: Vec<P>, edge_mode: EdgeMode<P>) -> Self {
+        assert_eq!(pixels.len(), width * height, "Expected {} pixels for a {}x{} image", width * height, width, height);
+        Image { width, height, pixels, edge_mode }
+    }
+
+    /// The pixels, row by row, for filling an image in parallel
+    pub(crate) fn pixels_mut(&mut self) -> &mut [P] {
+        &mut self.pixels
+    }
+
+    pub fn with_edge_mode(mut self, edge_mode: EdgeMode<P>) -> Self {
+        self.edge_mode = edge_mode;
+        self
+    }
+
     pub fn width(&self) -> usize {
         self.width
     }
@@ -43,40 +84,223 @@ impl<P: Copy + Add<Output=P> + Mul<Output=P>> Image<P> {
         self.height
     }
 
+    pub fn edge_mode(&self) -> EdgeMode<P> {
+        self.edge_mode
+    }
+
+    pub fn set_edge_mode(&mut self, edge_mode: EdgeMode<P>) {
+        self.edge_mode = edge_mode;
+    }
+
     pub fn get(&self, x: i64, y: i64) -> &P {
-        let index = self.pixel_index(x, y);
-        &self.pixels[index]
+        self.get_with_edge_mode(x, y, &self.edge_mode)
+    }
+
+    /// Reads a pixel using the given edge mode instead of the image's own
+    pub fn get_with_edge_mode<'a>(&'a self, x: i64, y: i64, edge_mode: &'a EdgeMode<P>) -> &'a P {
+        match self.pixel_index(x, y, edge_mode) {
+            Some(index) => &self.pixels[index],
+            None => match edge_mode {
+                EdgeMode::Constant(color) => color,
+                _ => unreachable!(),
+            },
+        }
     }
 
     pub fn set(&mut self, x: i64, y: i64, c: P) {
-        let index = self.pixel_index(x, y);
-        self.pixels[index] = c;
+        if let Some(index) = self.pixel_index(x, y, &self.edge_mode) {
+            self.pixels[index] = c;
+        }
+    }
+
+    /// Writes a pixel using the given edge mode instead of the image's own
+    pub fn set_with_edge_mode(&mut self, x: i64, y: i64, c: P, edge_mode: &EdgeMode<P>) {
+        if let Some(index) = self.pixel_index(x, y, edge_mode) {
+            self.pixels[index] = c;
+        }
     }
     
     pub fn add(&mut self, x: i64, y: i64, c: P) {
-        let index = self.pixel_index(x, y);
-        self.pixels[index] = self.pixels[index] + c;
+        if let Some(index) = self.pixel_index(x, y, &self.edge_mode) {
+            self.pixels[index] = self.pixels[index] + c;
+        }
+    }
+
+    /// Adds to a pixel using the given edge mode instead of the image's own
+    pub fn add_with_edge_mode(&mut self, x: i64, y: i64, c: P, edge_mode: &EdgeMode<P>) {
+        if let Some(index) = self.pixel_index(x, y, edge_mode) {
+            self.pixels[index] = self.pixels[index] + c;
+        }
     }
 
     pub fn scale(&mut self, x: i64, y: i64, c: P) {
-        let index = self.pixel_index(x, y);
-        self.pixels[index] = self.pixels[index] * c;
+        if let Some(index) = self.pixel_index(x, y, &self.edge_mode) {
+            self.pixels[index] = self.pixels[index] * c;
+        }
+    }
+
+    /// Creates a new image by converting every pixel of this one. A constant 
+    /// edge color is converted too, so the new image has the same edge mode.
+    pub fn map<Q: Copy + Add<Output=Q> + Mul<Output=Q>, F: Fn(P) -> Q>(&self, f: F) -> Image<Q> {
+        let edge_mode = match self.edge_mode {
+            EdgeMode::Wrap => EdgeMode::Wrap,
+            EdgeMode::Clamp => EdgeMode::Clamp,
+            EdgeMode::Mirror => EdgeMode::Mirror,
+            EdgeMode::Constant(color) => EdgeMode::Constant(f(color)),
+            EdgeMode::Panic => EdgeMode::Panic,
+        };
+
+        Image {
+            width: self.width,
+            height: self.height,
+            pixels: self.pixels.iter().map(|&pixel| f(pixel)).collect(),
+            edge_mode
+        }
+    }
+
+    /**
+     * Copies out the width x height region whose top-left corner is at (x, y). 
+     * The region may extend past the image, in which case those pixels come 
+     * from the edge mode; with Clamp or Mirror this doubles as padding that 
+     * extends the image's edges.
+     */
+    pub fn crop(&self, x: i64, y: i64, width: usize, height: usize) -> Image<P> {
+        let mut pixels = Vec::with_capacity(width * height);
+        for row in 0..height as i64 {
+            for column in 0..width as i64 {
+                pixels.push(*self.get(x + column, y + row));
+            }
+        }
+
+        Image { width, height, pixels, edge_mode: self.edge_mode }
+    }
+
+    /// Surrounds the image with a border of the given color, widths in pixels
+    pub fn pad(&self, left: usize, top: usize, right: usize, bottom: usize, color: P) -> Image<P> {
+        let mut padded = Image::from_color(left + self.width + right, top + self.height + bottom, color)
+            .with_edge_mode(self.edge_mode);
+
+        for x in 0..self.width {
+            for y in 0..self.height {
+                padded.set((left + x) as i64, (top + y) as i64, self.pixels[x + y * self.width]);
+            }
+        }
+
+        padded
     }
 
-    fn pixel_index(&self, x: i64, y: i64) -> usize {
-        wrap_around(x, self.width) + 
-        wrap_around(y, self.height) * self.width
+    /// None means the pixel is outside the image and has no stored value
+    fn pixel_index(&self, x: i64, y: i64, edge_mode: &EdgeMode<P>) -> Option<usize> {
+        let column = resolve_coordinate(x, self.width, edge_mode);
+        let row = resolve_coordinate(y, self.height, edge_mode);
+
+        match (column, row) {
+            (Some(column), Some(row)) => Some(column + row * self.width),
+            _ => {
+                if let EdgeMode::Panic = edge_mode {
+                    panic!("Pixel ({}, {}) is outside of the {}x{} image", x, y, self.width, self.height);
+                }
+                None
+            },
+        }
     }
 }
 
-fn wrap_around(num: i64, space: usize) -> usize {
-    let mut res = num;
+fn resolve_coordinate<P>(num: i64, space: usize, edge_mode: &EdgeMode<P>) -> Option<usize> {
     let space_i64 = space as i64;
+    let in_bounds = num >= 0 && num < space_i64;
+
+    match edge_mode {
+        EdgeMode::Wrap => Some(num.rem_euclid(space_i64) as usize),
+        EdgeMode::Clamp => Some(num.max(0).min(space_i64 - 1) as usize),
+        EdgeMode::Mirror => {
+            let reflected = num.rem_euclid(space_i64 * 2);
+            if reflected < space_i64 {
+                Some(reflected as usize)
+            } else {
+                Some((space_i64 * 2 - 1 - reflected) as usize)
+            }
+        },
+        EdgeMode::Constant(_) | EdgeMode::Panic => {
+            if in_bounds {
+                Some(num as usize)
+            } else {
+                None
+            }
+        },
+    }
+}
+
+impl Image<GrayscaleColor> {
+
+    /// Linearly rescales all values so that the lowest becomes 0 and the highest 1
+    pub fn normalize(&mut self) {
+        let min = self.pixels.iter().cloned().fold(f32::INFINITY, f32::min);
+        let max = self.pixels.iter().cloned().fold(f32::NEG_INFINITY, f32::max);
+        let range = max - min;
+
+        for pixel in self.pixels.iter_mut() {
+            *pixel = if range > 0.0 { (*pixel - min) / range } else { 0.5 };
+        }
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::{EdgeMode, Image};
+
+    fn row() -> Image<f32> {
+        let mut image = Image::from_color(3, 1, 0.0);
+        image.set(0, 0, 1.0);
+        image.set(1, 0, 2.0);
+        image.set(2, 0, 3.0);
+        image
+    }
+
+    #[test]
+    fn test_edge_modes() {
+        let image = row();
+        let read = |mode: EdgeMode<f32>| (-2..5).map(|x| *image.get_with_edge_mode(x, 0, &mode)).collect::<Vec<f32>>();
+
+        assert_eq!(read(EdgeMode::Wrap), vec![2.0, 3.0, 1.0, 2.0, 3.0, 1.0, 2.0]);
+        assert_eq!(read(EdgeMode::Clamp), vec![1.0, 1.0, 1.0, 2.0, 3.0, 3.0, 3.0]);
+        assert_eq!(read(EdgeMode::Mirror), vec![2.0, 1.0, 1.0, 2.0, 3.0, 3.0, 2.0]);
+        assert_eq!(read(EdgeMode::Constant(9.0)), vec![9.0, 9.0, 1.0, 2.0, 3.0, 9.0, 9.0]);
+    }
+
+    #[test]
+    fn test_constant_ignores_writes() {
+        let mut image = row().with_edge_mode(EdgeMode::Constant(0.0));
+        image.set(-1, 0, 5.0);
+        image.add(3, 0, 5.0);
+
+        assert_eq!(*image.get(-1, 0), 0.0);
+        assert_eq!(*image.get(0, 0), 1.0);
+        assert_eq!(*image.get(2, 0), 3.0);
+    }
 
-    while res < 0 {
-        res += space_i64;
+    #[test]
+    fn test_crop_and_pad() {
+        let image = row();
+
+        let cropped = image.crop(1, 0, 3, 1);
+        assert_eq!((cropped.width(), cropped.height()), (3, 1));
+        assert_eq!((*cropped.get(0, 0), *cropped.get(1, 0), *cropped.get(2, 0)), (2.0, 3.0, 1.0));
+
+        let padded = image.pad(1, 2, 0, 1, 7.0);
+        assert_eq!((padded.width(), padded.height()), (4, 4));
+        assert_eq!(*padded.get(0, 2), 7.0);
+        assert_eq!(*padded.get(1, 2), 1.0);
+        assert_eq!(*padded.get(3, 2), 3.0);
+        assert_eq!(*padded.get(2, 0), 7.0);
+        assert_eq!(*padded.get(2, 3), 7.0);
     }
-    res = res % space_i64;
 
-    return res as usize;
-}
\ No newline at end of file
+    #[test]
+    #[should_panic]
+    fn test_panic() {
+        let image = row().with_edge_mode(EdgeMode::Panic);
+        image.get(3, 0);
+    }
+}
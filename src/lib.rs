@@ -1,4 +1,11 @@
-
 pub mod image;
+pub mod color;
+pub mod color_ramp;
+pub mod blend;
+pub mod sampling;
 pub mod generators;
+pub mod filters;
+pub mod noise;
+pub mod pipeline;
+pub mod graph;
 pub mod utils;
\ No newline at end of file
@@ -1,48 +1,294 @@
 extern crate image;
+extern crate structopt;
+
+use std::path::{Path, PathBuf};
+use std::process;
+use std::str::FromStr;
 
 use image::{ImageBuffer, Rgb};
-use image_gen::{generators::add_perlin_noise, image::{Image,float_to_u8}};
-use image_gen::generators::{generate_diamond_square, generate_bricks, generate_perlin_noise, HillShape, generate_hill};
+use structopt::StructOpt;
+
+use image_gen::color;
+use image_gen::color_ramp::ColorRamp;
+use image_gen::image::{GrayscaleColor, Image, float_to_u8};
+use image_gen::pipeline::Pipeline;
+use image_gen::generators::{
+    Generator, BricksParams, DiamondSquareParams, FbmParams, HillParams, HillShape, PerlinParams,
+    SimplexParams, WorleyParams, WorleyChannel, DistanceMetric
+};
 use image_gen::utils::vec2::Vec2;
 
-const RESOLUTION: usize = 1024;
+#[derive(StructOpt)]
+#[structopt(name = "image_gen", about = "Generates noise and heightmap images")]
+struct Options {
+    /// Width of the output image, in pixels [default: 1024, or the pipeline's width]
+    #[structopt(long, parse(try_from_str = parse_count))]
+    width: Option<usize>,
+
+    /// Height of the output image, in pixels [default: same as width, or the pipeline's height]
+    #[structopt(long, parse(try_from_str = parse_count))]
+    height: Option<usize>,
+
+    /// Seed for the random generators; the same seed always gives the same image
+    #[structopt(long, default_value = "0")]
+    seed: u64,
+
+    #[structopt(long, short, default_value = "output.png", parse(from_os_str))]
+    output: PathBuf,
+
+    /// Colorizes the result using a built-in ramp (terrain, viridis, magma,
+    /// grayscale) or a ramp file
+    #[structopt(long)]
+    ramp: Option<String>,
+
+    #[structopt(subcommand)]
+    command: Command,
+}
+
+#[derive(StructOpt)]
+enum Command {
+    /// Renders a pipeline file; seeds come from the file rather than --seed
+    Render {
+        #[structopt(parse(from_os_str))]
+        pipeline: PathBuf,
+    },
+    Bricks {
+        #[structopt(long, default_value = "2", parse(try_from_str = parse_count))]
+        bricks_x: usize,
+        #[structopt(long, default_value = "8", parse(try_from_str = parse_count))]
+        bricks_y: usize,
+        #[structopt(long, default_value = "0.1")]
+        bevel_x: f32,
+        #[structopt(long, default_value = "0.4")]
+        bevel_y: f32,
+        #[structopt(long, default_value = "0.1")]
+        gap_x: f32,
+        #[structopt(long, default_value = "0.1")]
+        gap_y: f32,
+    },
+    Hill {
+        /// Horizontal position of the center, from 0 up to (but not including) 1
+        #[structopt(long, default_value = "0.5", parse(try_from_str = parse_location))]
+        x: f32,
+        /// Vertical position of the center, from 0 up to (but not including) 1
+        #[structopt(long, default_value = "0.5", parse(try_from_str = parse_location))]
+        y: f32,
+        /// constant, linear, sphere or smooth
+        #[structopt(long, default_value = "smooth", parse(try_from_str = parse_hill_shape))]
+        shape: HillShape,
+        /// Radius, relative to the image width
+        #[structopt(long, default_value = "0.5", parse(try_from_str = parse_positive))]
+        radius: f32,
+        #[structopt(long, default_value = "1.0")]
+        elevation: f32,
+    },
+    Perlin {
+        #[structopt(long, default_value = "13", parse(try_from_str = parse_grid_size))]
+        grid_size: usize,
+        #[structopt(long, default_value = "0.5")]
+        midpoint: f32,
+        #[structopt(long, default_value = "0.5")]
+        scale: f32,
+        /// Repeats the noise every grid_size - 1 cells, so the image tiles
+        #[structopt(long)]
+        tileable: bool,
+    },
+    DiamondSquare {
+        #[structopt(long, default_value = "1.0", parse(try_from_str = parse_unit))]
+        variance: f32,
+        #[structopt(long, default_value = "1.0", parse(try_from_str = parse_unit))]
+        coarseness: f32,
+        #[structopt(long)]
+        tileable: bool,
+    },
+    Fbm {
+        #[structopt(long, default_value = "3", parse(try_from_str = parse_grid_size))]
+        grid_size: usize,
+        #[structopt(long, default_value = "6", parse(try_from_str = parse_count))]
+        octaves: usize,
+        #[structopt(long, default_value = "2.0", parse(try_from_str = parse_lacunarity))]
+        lacunarity: f32,
+        #[structopt(long, default_value = "0.5")]
+        persistence: f32,
+        #[structopt(long)]
+        tileable: bool,
+    },
+    Simplex {
+        /// Rough width of a single bump, in pixels
+        #[structopt(long, default_value = "64.0", parse(try_from_str = parse_positive))]
+        feature_size: f32,
+        #[structopt(long, default_value = "0.5")]
+        midpoint: f32,
+        #[structopt(long, default_value = "0.5")]
+        scale: f32,
+    },
+    Worley {
+        #[structopt(long, default_value = "8", parse(try_from_str = parse_count))]
+        cells_x: usize,
+        #[structopt(long, default_value = "8", parse(try_from_str = parse_count))]
+        cells_y: usize,
+        #[structopt(long, default_value = "1.0", parse(try_from_str = parse_unit))]
+        jitter: f32,
+        /// euclidean, manhattan or chebyshev
+        #[structopt(long, default_value = "euclidean", parse(try_from_str = parse_distance_metric))]
+        distance: DistanceMetric,
+        /// f1, f2, f2-f1 or cell-id
+        #[structopt(long, default_value = "f1", parse(try_from_str = parse_worley_channel))]
+        channel: WorleyChannel,
+        #[structopt(long)]
+        tileable: bool,
+    },
+}
 
 fn main() {
-    
+    let options = Options::from_args();
+    let seed = options.seed;
+
+    let ramp = options.ramp.as_ref().map(|ramp| load_ramp(ramp));
+
     println!("Generating...");
     let start = std::time::SystemTime::now();
 
-    // let image = generate_diamond_square(RESOLUTION, 1.0, 1.0);
-    // let image = generate_perlin_noise(RESOLUTION, 13, 0.5, 0.5);
-    // let image = generate_bricks(RESOLUTION, 2, 8, Vec2 { x: 0.1, y: 0.4 }, Vec2 { x: 0.1, y: 0.1 });
-    // let image = generate_hill(RESOLUTION, (0.5, 0.5), HillShape::Smooth, 0.5, 1.0);
-
-    let mut image =  generate_hill(RESOLUTION, (0.5, 0.5), HillShape::Smooth, 0.5, 1.0);
-    add_perlin_noise(&mut image, 13, 0.1);
+    let image = generate(options.command, options.width, options.height, seed);
 
     let end = std::time::SystemTime::now();
     let total = end.duration_since(start).unwrap();
     println!("Took {}s", total.as_millis() as f32 / 1000.0);
 
     println!("Writing to png...");
-    save_image(&image, "output.png");
+    let colored = match ramp {
+        Some(ramp) => ramp.colorize(&image),
+        None => image.to_rgb(),
+    };
+    save_image(&colored, &options.output);
     println!("done");
 }
 
-fn save_image(image: &Image<f32>, path: &str) {
-    let image_buffer = image_to_buffer(&image);
-    image_buffer.save(path).unwrap();
+/// Runs the chosen command; a pipeline brings its own size, which width and height override
+fn generate(command: Command, width: Option<usize>, height: Option<usize>, seed: u64) -> Image<GrayscaleColor> {
+    let generator: Box<dyn Generator> = match command {
+        Command::Render { pipeline } => {
+            let mut pipeline = Pipeline::load(&pipeline).unwrap_or_else(|error| exit_with_error(&error.to_string()));
+            pipeline.width = width.unwrap_or(pipeline.width);
+            pipeline.height = height.unwrap_or(pipeline.height);
+
+            return pipeline.render();
+        },
+        Command::Bricks { bricks_x, bricks_y, bevel_x, bevel_y, gap_x, gap_y } =>
+            Box::new(BricksParams { bricks_x, bricks_y, bevel: Vec2 { x: bevel_x, y: bevel_y }, gap: Vec2 { x: gap_x, y: gap_y } }),
+        Command::Hill { x, y, shape, radius, elevation } =>
+            Box::new(HillParams { location: (x, y), shape, radius, height: elevation }),
+        Command::Perlin { grid_size, midpoint, scale, tileable } => {
+            let period = if tileable { Some((grid_size - 1, grid_size - 1)) } else { None };
+            Box::new(PerlinParams { grid_size, midpoint, scale, seed, period })
+        },
+        Command::DiamondSquare { variance, coarseness, tileable } =>
+            Box::new(DiamondSquareParams { variance, coarseness, seed, tileable }),
+        Command::Fbm { grid_size, octaves, lacunarity, persistence, tileable } =>
+            Box::new(FbmParams { grid_size, octaves, lacunarity, persistence, seed, tileable }),
+        Command::Simplex { feature_size, midpoint, scale } =>
+            Box::new(SimplexParams { feature_size, midpoint, scale, seed }),
+        Command::Worley { cells_x, cells_y, jitter, distance, channel, tileable } =>
+            Box::new(WorleyParams { cells_x, cells_y, jitter, distance, channel, tileable, seed }),
+    };
+
+    let width = width.unwrap_or(1024);
+    let height = height.unwrap_or(width);
+    generator.generate(width, height)
+}
+
+/// Ramps can be given as the name of a preset or as a path to a ramp file
+fn load_ramp(ramp: &str) -> ColorRamp {
+    match ColorRamp::preset(ramp) {
+        Some(preset) => preset,
+        None => ColorRamp::load(ramp).unwrap_or_else(|error| exit_with_error(&error.to_string())),
+    }
+}
+
+/// Parses a number and checks it against a requirement, so bad arguments are reported like any other usage error
+fn parse_checked<T: FromStr>(s: &str, valid: impl Fn(&T) -> bool, requirement: &str) -> Result<T, String> {
+    let value = s.parse().map_err(|_| format!("'{}' isn't a valid number", s))?;
+    if valid(&value) {
+        Ok(value)
+    } else {
+        Err(format!("{} {}", s, requirement))
+    }
+}
+
+fn parse_count(s: &str) -> Result<usize, String> {
+    parse_checked(s, |&count| count >= 1, "must be at least 1")
+}
+
+fn parse_grid_size(s: &str) -> Result<usize, String> {
+    parse_checked(s, |&size| size >= 2, "must be at least 2")
+}
+
+fn parse_positive(s: &str) -> Result<f32, String> {
+    parse_checked(s, |&value: &f32| value > 0.0 && value.is_finite(), "must be greater than 0")
+}
+
+fn parse_unit(s: &str) -> Result<f32, String> {
+    parse_checked(s, |value| (0.0..=1.0).contains(value), "must be between 0 and 1")
+}
+
+fn parse_location(s: &str) -> Result<f32, String> {
+    parse_checked(s, |value| (0.0..1.0).contains(value), "must be at least 0 and less than 1")
+}
+
+fn parse_lacunarity(s: &str) -> Result<f32, String> {
+    parse_checked(s, |&value: &f32| value >= 1.0 && value.is_finite(), "must be at least 1")
+}
+
+fn parse_hill_shape(s: &str) -> Result<HillShape, String> {
+    match s {
+        "constant" => Ok(HillShape::Constant),
+        "linear" => Ok(HillShape::Linear),
+        "sphere" => Ok(HillShape::Sphere),
+        "smooth" => Ok(HillShape::Smooth),
+        _ => Err(format!("unknown hill shape '{}'", s)),
+    }
+}
+
+fn parse_distance_metric(s: &str) -> Result<DistanceMetric, String> {
+    match s {
+        "euclidean" => Ok(DistanceMetric::Euclidean),
+        "manhattan" => Ok(DistanceMetric::Manhattan),
+        "chebyshev" => Ok(DistanceMetric::Chebyshev),
+        _ => Err(format!("unknown distance metric '{}'", s)),
+    }
+}
+
+fn parse_worley_channel(s: &str) -> Result<WorleyChannel, String> {
+    match s {
+        "f1" => Ok(WorleyChannel::F1),
+        "f2" => Ok(WorleyChannel::F2),
+        "f2-f1" => Ok(WorleyChannel::F2MinusF1),
+        "cell-id" => Ok(WorleyChannel::CellId),
+        _ => Err(format!("unknown Worley channel '{}'", s)),
+    }
+}
+
+fn exit_with_error(message: &str) -> ! {
+    eprintln!("error: {}", message);
+    process::exit(1);
+}
+
+fn save_image(image: &Image<color::Rgb>, path: &Path) {
+    let image_buffer = image_to_buffer(image);
+    if let Err(error) = image_buffer.save(path) {
+        exit_with_error(&format!("couldn't write {}: {}", path.display(), error));
+    }
 }
 
-fn image_to_buffer(image: &Image<f32>) -> ImageBuffer<Rgb<u8>,Vec<u8>> {
-    let mut image_buffer: ImageBuffer<Rgb<u8>,Vec<u8>> = ImageBuffer::new(RESOLUTION as u32, RESOLUTION as u32);
+fn image_to_buffer(image: &Image<color::Rgb>) -> ImageBuffer<Rgb<u8>,Vec<u8>> {
+    let mut image_buffer: ImageBuffer<Rgb<u8>,Vec<u8>> = ImageBuffer::new(image.width() as u32, image.height() as u32);
 
-    for x in 0..RESOLUTION {
-        for y in 0..RESOLUTION {
+    for x in 0..image.width() {
+        for y in 0..image.height() {
             let color = *image.get(x as i64, y as i64);
-            image_buffer.get_pixel_mut(x as u32, y as u32).data = [ float_to_u8(color), float_to_u8(color), float_to_u8(color) ];
+            image_buffer.get_pixel_mut(x as u32, y as u32).data = [ float_to_u8(color.r), float_to_u8(color.g), float_to_u8(color.b) ];
         }
     }
 
     return image_buffer;
-}
\ No newline at end of file
+}
@@ -0,0 +1,115 @@
+use crate::utils::random::hash_coords;
+use crate::utils::vec2::Vec2;
+use crate::utils::vec3::Vec3;
+use super::{NoiseFn, NoiseFn3, Seeded};
+
+/**
+ * Fractal Brownian motion over any other noise: several octaves of it summed
+ * together, each one finer and weaker than the last. Lacunarity is how much 
+ * finer each octave gets, persistence is how much weaker. The sum is divided 
+ * by the total weight, so it stays in the same range as the underlying noise.
+ * 
+ * Each octave is the underlying noise reseeded with its seed hashed with the 
+ * octave's index, the same seeds the tileable fBm generator uses, so no two 
+ * octaves share a lattice even when the noise is periodic.
+ */
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Fbm<N> {
+    pub noise: N,
+    pub octaves: usize,
+    pub lacunarity: f32,
+    pub persistence: f32,
+}
+
+impl<N> Fbm<N> {
+
+    pub fn new(noise: N, octaves: usize) -> Self {
+        Fbm { noise, octaves, lacunarity: 2.0, persistence: 0.5 }
+    }
+}
+
+impl<N: Seeded> Fbm<N> {
+
+    /// Calls f with each octave's noise and frequency, and averages the results by weight
+    fn sum<F: Fn(&N, f32) -> f32>(&self, f: F) -> f32 {
+        assert!(self.octaves >= 1, "There must be at least one octave");
+
+        let mut frequency = 1.0;
+        let mut amplitude = 1.0;
+        let mut total = 0.0;
+        let mut total_amplitude = 0.0;
+        for octave in 0..self.octaves {
+            let noise = self.noise.with_seed(hash_coords(self.noise.seed(), &[octave as i64]));
+
+            total += f(&noise, frequency) * amplitude;
+            total_amplitude += amplitude;
+
+            frequency *= self.lacunarity;
+            amplitude *= self.persistence;
+        }
+
+        total / total_amplitude
+    }
+}
+
+impl<N: Seeded> Seeded for Fbm<N> {
+
+    fn seed(&self) -> u64 {
+        self.noise.seed()
+    }
+
+    fn with_seed(&self, seed: u64) -> Self {
+        Fbm { noise: self.noise.with_seed(seed), ..*self }
+    }
+}
+
+impl<N: NoiseFn + Seeded> NoiseFn for Fbm<N> {
+
+    fn sample(&self, p: Vec2) -> f32 {
+        self.sum(|noise, frequency| noise.sample(&p * frequency))
+    }
+}
+
+impl<N: NoiseFn3 + Seeded> NoiseFn3 for Fbm<N> {
+
+    fn sample(&self, p: Vec3) -> f32 {
+        self.sum(|noise, frequency| noise.sample(&p * frequency))
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::Fbm;
+    use crate::noise::{NoiseFn, Perlin, ValueNoise};
+    use crate::utils::random::hash_coords;
+    use crate::utils::vec2::Vec2;
+
+    #[test]
+    fn test_range() {
+        let fbm = Fbm::new(ValueNoise::new(8), 5);
+
+        for x in 0..100 {
+            for y in 0..100 {
+                let value = fbm.sample(Vec2 { x: x as f32 * 0.13, y: y as f32 * 0.07 });
+                assert!(value >= -1.0 && value <= 1.0);
+            }
+        }
+    }
+
+    #[test]
+    fn test_octave_seeds() {
+        // with equal weights and frequencies, the octaves' average is all that's left
+        let fbm = Fbm { noise: Perlin::new(8), octaves: 2, lacunarity: 1.0, persistence: 1.0 };
+        let first = Perlin::new(hash_coords(8, &[0]));
+        let second = Perlin::new(hash_coords(8, &[1]));
+
+        for x in 0..20 {
+            for y in 0..20 {
+                let p = Vec2 { x: x as f32 * 0.31, y: y as f32 * 0.23 };
+                let expected = (first.sample(p) + second.sample(p)) / 2.0;
+                assert_eq!(fbm.sample(p).to_bits(), expected.to_bits());
+            }
+        }
+    }
+}
@@ -0,0 +1,285 @@
+use std::f32::consts::PI;
+
+use crate::utils::interpolation::{lerp, smootherstep};
+use crate::utils::random::{hash_coords, hash_to_unit};
+use crate::utils::vec2::Vec2;
+use crate::utils::vec3::Vec3;
+use super::{NoiseFn, NoiseFn3, Seeded};
+
+/**
+ * Perlin noise that can be sampled at any point. Each lattice point's gradient 
+ * is hashed from its coordinates, so the noise goes on forever. Lattice points 
+ * are one unit apart, and values are in -1..1.
+ * 
+ * When period is set, lattice coordinates wrap around after that many cells 
+ * across and down, so the noise repeats exactly every period units.
+ */
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Perlin {
+    pub seed: u64,
+    pub period: Option<(usize, usize)>,
+}
+
+impl Perlin {
+
+    pub fn new(seed: u64) -> Self {
+        Perlin { seed, period: None }
+    }
+
+    pub fn periodic(seed: u64, period: (usize, usize)) -> Self {
+        assert!(period.0 >= 1 && period.1 >= 1, "Period must be at least one cell");
+        Perlin { seed, period: Some(period) }
+    }
+}
+
+impl NoiseFn for Perlin {
+
+    fn sample(&self, p: Vec2) -> f32 {
+        let period = self.period.map(|(x, y)| [x as i64, y as i64]);
+        gradient_noise(self.seed, [p.x, p.y], period) * SCALE_2D
+    }
+}
+
+impl Seeded for Perlin {
+
+    fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    fn with_seed(&self, seed: u64) -> Self {
+        Perlin { seed, ..*self }
+    }
+}
+
+/**
+ * Three-dimensional Perlin noise, for when a third axis is needed: time for
+ * animated textures, or depth for solid textures and noise on a sphere's
+ * surface. Lattice points are one unit apart, so scale positions to change
+ * the feature size. Values are roughly -1..1, and exactly 0 on lattice points.
+ */
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Perlin3 {
+    pub seed: u64,
+}
+
+impl Perlin3 {
+
+    pub fn new(seed: u64) -> Self {
+        Perlin3 { seed }
+    }
+}
+
+impl NoiseFn3 for Perlin3 {
+
+    fn sample(&self, p: Vec3) -> f32 {
+        gradient_noise(self.seed, [p.x, p.y, p.z], None) * SCALE_3D
+    }
+}
+
+impl Seeded for Perlin3 {
+
+    fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    fn with_seed(&self, seed: u64) -> Self {
+        Perlin3 { seed }
+    }
+}
+
+/**
+ * Four-dimensional Perlin noise. Mostly useful for looping animations (see
+ * LoopingPerlin), or for animating 3D noise over time. Same range and lattice
+ * spacing as Perlin3.
+ */
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Perlin4 {
+    pub seed: u64,
+}
+
+impl Perlin4 {
+
+    pub fn new(seed: u64) -> Self {
+        Perlin4 { seed }
+    }
+
+    pub fn sample(&self, p: Vec3, w: f32) -> f32 {
+        gradient_noise(self.seed, [p.x, p.y, p.z, w], None) * SCALE_4D
+    }
+}
+
+/**
+ * 2D noise that changes over time and loops perfectly. Time runs from 0 to 1,
+ * after which the noise is back where it started. This works by walking a
+ * circle through the third and fourth dimensions of 4D noise; the radius of
+ * the circle is how much the noise changes over one loop.
+ */
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct LoopingPerlin {
+    pub seed: u64,
+    pub radius: f32,
+}
+
+impl LoopingPerlin {
+
+    pub fn new(seed: u64, radius: f32) -> Self {
+        LoopingPerlin { seed, radius }
+    }
+
+    pub fn sample(&self, p: Vec2, time: f32) -> f32 {
+        let angle = time * 2.0 * PI;
+        let circle = Vec3 { x: p.x, y: p.y, z: angle.cos() * self.radius };
+
+        Perlin4::new(self.seed).sample(circle, angle.sin() * self.radius)
+    }
+}
+
+// The largest value gradient noise can reach is at the center of a cell whose 
+// corner gradients all point at it. Every corner is then sqrt(n)/2 away along 
+// the diagonal, so in 2D, where gradients are unit vectors at any angle, the 
+// peak is sqrt(2)/2. In more dimensions gradients have one zero component and 
+// the rest ±1 (Ken Perlin's "improved" gradient set), so each corner's dot 
+// product is at most (n - 1)/2. Dividing by the peak brings the output into -1..1
+const SCALE_2D: f32 = 2.0 / std::f32::consts::SQRT_2;
+const SCALE_3D: f32 = 1.0;
+const SCALE_4D: f32 = 2.0 / 3.0;
+
+// enough corners for a 4D cell
+const MAX_CORNERS: usize = 16;
+
+/**
+ * Perlin noise in N dimensions, up to 4. Each lattice corner of the cell
+ * containing point gets a pseudo-random gradient hashed from its coordinates,
+ * so no tables need to be built and any point can be sampled on its own. 
+ * With a period, corners are wrapped before hashing, so the noise repeats.
+ */
+fn gradient_noise<const N: usize>(seed: u64, point: [f32; N], period: Option<[i64; N]>) -> f32 {
+    let corners = 1 << N;
+    assert!(corners <= MAX_CORNERS, "Gradient noise goes up to 4 dimensions");
+
+    let mut cell = [0; N];
+    let mut offset = [0.0; N];
+    for axis in 0..N {
+        cell[axis] = point[axis].floor() as i64;
+        offset[axis] = point[axis] - cell[axis] as f32;
+    }
+
+    // contribution of every corner; bit i of the index is set for the far corner along axis i
+    let mut values = [0.0; MAX_CORNERS];
+    for (index, value) in values.iter_mut().take(corners).enumerate() {
+        let far = |axis: usize| ((index >> axis) & 1) as i64;
+
+        let mut corner = cell;
+        let mut distance = offset;
+        for axis in 0..N {
+            corner[axis] += far(axis);
+            distance[axis] -= far(axis) as f32;
+        }
+
+        if let Some(period) = period {
+            for axis in 0..N {
+                corner[axis] = corner[axis].rem_euclid(period[axis]);
+            }
+        }
+
+        *value = dot_gradient(hash_coords(seed, &corner), &distance);
+    }
+
+    // blend pairs of corners together one axis at a time, halving the values in place
+    let mut remaining = corners;
+    for axis_offset in offset.iter() {
+        let t = smootherstep(0.0, 1.0, *axis_offset);
+        remaining /= 2;
+        for i in 0..remaining {
+            values[i] = lerp(values[i * 2], values[i * 2 + 1], t);
+        }
+    }
+
+    values[0]
+}
+
+/// Dot product of a corner's hashed gradient with the distance from that corner
+fn dot_gradient(hash: u64, distance: &[f32]) -> f32 {
+    // axis-aligned gradients would show the grid in 2D, so pick any angle
+    if distance.len() == 2 {
+        let angle = hash_to_unit(hash) * 2.0 * PI;
+        return distance[0] * angle.cos() + distance[1] * angle.sin();
+    }
+
+    // the gradient skips one axis, and has a hashed sign on every other
+    let skipped = (hash % distance.len() as u64) as usize;
+
+    let mut dot = 0.0;
+    for (axis, axis_distance) in distance.iter().enumerate() {
+        if axis != skipped {
+            let sign = if (hash >> (8 + axis)) & 1 == 0 { 1.0 } else { -1.0 };
+            dot += axis_distance * sign;
+        }
+    }
+    dot
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::{LoopingPerlin, Perlin, Perlin3, Perlin4};
+    use crate::noise::{NoiseFn, NoiseFn3};
+    use crate::utils::random::{hash_coords, hash_to_unit};
+    use crate::utils::vec2::Vec2;
+    use crate::utils::vec3::Vec3;
+
+    fn random_point(i: i64) -> Vec3 {
+        let coordinate = |axis: i64| hash_to_unit(hash_coords(0, &[i, axis])) * 20.0 - 10.0;
+        Vec3 { x: coordinate(0), y: coordinate(1), z: coordinate(2) }
+    }
+
+    #[test]
+    fn test_range() {
+        let noise_2 = Perlin::new(4);
+        let noise_3 = Perlin3::new(4);
+        let noise_4 = Perlin4::new(4);
+
+        for i in 0..5000 {
+            let p = random_point(i);
+            assert!(noise_2.sample(Vec2 { x: p.x, y: p.y }).abs() <= 1.0, "{:?}", p);
+            assert!(noise_3.sample(p).abs() <= 1.0, "{:?}", p);
+            assert!(noise_4.sample(p, p.x - p.y).abs() <= 1.0, "{:?}", p);
+        }
+
+        assert_eq!(noise_3.sample(Vec3 { x: 3.0, y: -2.0, z: 7.0 }), 0.0);
+    }
+
+    #[test]
+    fn test_periodic() {
+        let noise = Perlin::periodic(5, (6, 4));
+
+        // positions on a grid of 1/32ths, so shifting them by whole periods is exact
+        for i in 0..1000 {
+            let p = Vec2 { x: (i % 37) as f32 / 32.0 - 0.5, y: (i / 37) as f32 / 32.0 - 0.25 };
+            let value = noise.sample(p);
+
+            assert_eq!(value.to_bits(), noise.sample(Vec2 { x: p.x - 6.0, y: p.y }).to_bits(), "{:?}", p);
+            assert_eq!(value.to_bits(), noise.sample(Vec2 { x: p.x, y: p.y + 8.0 }).to_bits(), "{:?}", p);
+        }
+    }
+
+    #[test]
+    fn test_continuous() {
+        let noise = Perlin3::new(11);
+
+        for i in 0..1000 {
+            let p = random_point(i);
+            let nudged = Vec3 { x: p.x + 0.001, y: p.y, z: p.z - 0.001 };
+            assert!((noise.sample(p) - noise.sample(nudged)).abs() < 0.01);
+        }
+    }
+
+    #[test]
+    fn test_loops() {
+        let noise = LoopingPerlin::new(2, 1.5);
+        let p = Vec2 { x: 3.7, y: 1.2 };
+
+        assert!((noise.sample(p, 0.0) - noise.sample(p, 1.0)).abs() < 0.0001);
+        assert!((noise.sample(p, 0.0) - noise.sample(p, 0.5)).abs() > 0.0);
+    }
+}
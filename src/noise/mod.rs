@@ -0,0 +1,147 @@
+mod gradient;
+mod value;
+mod simplex;
+pub(crate) mod worley;
+mod fbm;
+
+pub use gradient::{Perlin, Perlin3, Perlin4, LoopingPerlin};
+pub use value::ValueNoise;
+pub use simplex::Simplex;
+pub use worley::{Worley, WorleyChannel, DistanceMetric};
+pub use fbm::Fbm;
+
+use crate::image::{GrayscaleColor, Image};
+use crate::utils::vec2::Vec2;
+use crate::utils::vec3::Vec3;
+
+/**
+ * A noise function that can be evaluated at any point in the plane, without 
+ * filling an image. Implementations are stateless apart from their parameters, 
+ * so the same point always gives the same value, and points can be sampled in 
+ * any order (or from any thread).
+ * 
+ * Positions are in the noise's own units, usually one lattice cell or 
+ * feature per unit; scale positions to change the feature size. Most noise 
+ * falls in roughly -1..1.
+ */
+pub trait NoiseFn: Sync {
+
+    fn sample(&self, p: Vec2) -> f32;
+}
+
+/// Same as NoiseFn, but over three dimensions
+pub trait NoiseFn3: Sync {
+
+    fn sample(&self, p: Vec3) -> f32;
+}
+
+/**
+ * Noise whose seed can be swapped for another while keeping its other 
+ * parameters. Fbm uses this to give each octave a seed of its own.
+ */
+pub trait Seeded: Sized {
+
+    fn seed(&self) -> u64;
+
+    fn with_seed(&self, seed: u64) -> Self;
+}
+
+impl<N: NoiseFn + ?Sized> NoiseFn for &N {
+
+    fn sample(&self, p: Vec2) -> f32 {
+        (**self).sample(p)
+    }
+}
+
+impl<N: NoiseFn3 + ?Sized> NoiseFn3 for &N {
+
+    fn sample(&self, p: Vec3) -> f32 {
+        (**self).sample(p)
+    }
+}
+
+/**
+ * Adds noise to every pixel of the image. Pixel (x, y) samples the noise at 
+ * (x, y) / feature_size, so feature size is roughly the width of one lattice 
+ * cell in pixels, and the sampled value is multiplied by scale.
+ */
+pub fn add_noise<N: NoiseFn>(image: &mut Image<GrayscaleColor>, noise: &N, feature_size: f32, scale: f32) {
+    assert!(feature_size > 0.0, "Feature size must be greater than 0");
+
+    add_in_parallel(image, thread_count(), |x, y| {
+        noise.sample(Vec2 { x: x as f32 / feature_size, y: y as f32 / feature_size }) * scale
+    });
+}
+
+/**
+ * Adds noise stretched so that exactly cells.0 by cells.1 noise units cover the 
+ * image, which lines a lattice up with the image's edges (and makes periodic 
+ * noise tile). The sampled value is multiplied by scale.
+ */
+pub fn add_fitted_noise<N: NoiseFn>(image: &mut Image<GrayscaleColor>, noise: &N, cells: (f32, f32), scale: f32) {
+    let cell_width = image.width() as f32 / cells.0;
+    let cell_height = image.height() as f32 / cells.1;
+
+    add_in_parallel(image, thread_count(), |x, y| {
+        noise.sample(Vec2 { x: x as f32 / cell_width, y: y as f32 / cell_height }) * scale
+    });
+}
+
+fn thread_count() -> usize {
+    std::thread::available_parallelism().map(|count| count.get()).unwrap_or(1)
+}
+
+/**
+ * Adds value(x, y) to every pixel, with the rows split into one band per 
+ * thread. Each pixel's value depends only on its coordinates and each band 
+ * writes only its own pixels, so the result is the same bits no matter how 
+ * many threads there are or how they're scheduled.
+ */
+fn add_in_parallel<F: Fn(usize, usize) -> f32 + Sync>(image: &mut Image<GrayscaleColor>, threads: usize, value: F) {
+    let width = image.width();
+    let height = image.height();
+    if width == 0 || height == 0 {
+        return;
+    }
+
+    let rows_per_band = height.div_ceil(threads);
+    let value = &value;
+
+    crossbeam::scope(|scope| {
+        for (band, pixels) in image.pixels_mut().chunks_mut(width * rows_per_band).enumerate() {
+            scope.spawn(move |_| {
+                for (i, pixel) in pixels.iter_mut().enumerate() {
+                    *pixel += value(i % width, band * rows_per_band + i / width);
+                }
+            });
+        }
+    }).unwrap();
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::{add_in_parallel, Fbm, NoiseFn, Perlin};
+    use crate::image::Image;
+    use crate::utils::vec2::Vec2;
+
+    #[test]
+    fn test_threads_match_serial() {
+        let noise = Fbm::new(Perlin::new(12), 5);
+        let fill = |threads: usize| {
+            let mut image = Image::from_color(61, 47, 0.25);
+            add_in_parallel(&mut image, threads, |x, y| noise.sample(Vec2 { x: x as f32 / 9.0, y: y as f32 / 7.0 }));
+            image
+        };
+
+        let serial = fill(1);
+        for &threads in [2, 5, 8, 64].iter() {
+            let threaded = fill(threads);
+            for x in 0..61 {
+                for y in 0..47 {
+                    assert_eq!(serial.get(x, y).to_bits(), threaded.get(x, y).to_bits(), "{} threads at ({}, {})", threads, x, y);
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,197 @@
+use crate::utils::vec2::Vec2;
+use super::{NoiseFn, Seeded};
+
+/**
+ * OpenSimplex2 noise: smoother and less grid-aligned than Perlin noise. 
+ * Values fall in roughly -1..1.
+ */
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Simplex {
+    pub seed: u64,
+}
+
+impl Simplex {
+
+    pub fn new(seed: u64) -> Self {
+        Simplex { seed }
+    }
+}
+
+impl NoiseFn for Simplex {
+
+    fn sample(&self, p: Vec2) -> f32 {
+        noise_2d(self.seed as i64, p.x as f64, p.y as f64)
+    }
+}
+
+impl Seeded for Simplex {
+
+    fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    fn with_seed(&self, seed: u64) -> Self {
+        Simplex { seed }
+    }
+}
+
+// Adapted from the reference OpenSimplex2 (fast variant) by K.jpg
+const PRIME_X: i64 = 0x5205402B9270C86F;
+const PRIME_Y: i64 = 0x598CD327003817B5;
+const HASH_MULTIPLIER: i64 = 0x53A3F72DEEC546F5;
+
+const SKEW_2D: f64 = 0.366025403784439;
+const UNSKEW_2D: f64 = -0.21132486540518713;
+const R_SQUARED_2D: f32 = 0.5;
+
+const N_GRADS_2D_EXPONENT: i64 = 7;
+const NORMALIZER_2D: f64 = 0.01001634121365712;
+
+const BASE_GRADIENTS_2D: [(f64, f64); 24] = [
+    ( 0.38268343236509,   0.923879532511287),
+    ( 0.923879532511287,  0.38268343236509),
+    ( 0.923879532511287, -0.38268343236509),
+    ( 0.38268343236509,  -0.923879532511287),
+    (-0.38268343236509,  -0.923879532511287),
+    (-0.923879532511287, -0.38268343236509),
+    (-0.923879532511287,  0.38268343236509),
+    (-0.38268343236509,   0.923879532511287),
+    ( 0.130526192220052,  0.99144486137381),
+    ( 0.608761429008721,  0.793353340291235),
+    ( 0.793353340291235,  0.608761429008721),
+    ( 0.99144486137381,   0.130526192220051),
+    ( 0.99144486137381,  -0.130526192220051),
+    ( 0.793353340291235, -0.60876142900872),
+    ( 0.608761429008721, -0.793353340291235),
+    ( 0.130526192220052, -0.99144486137381),
+    (-0.130526192220052, -0.99144486137381),
+    (-0.608761429008721, -0.793353340291235),
+    (-0.793353340291235, -0.608761429008721),
+    (-0.99144486137381,  -0.130526192220052),
+    (-0.99144486137381,   0.130526192220051),
+    (-0.793353340291235,  0.608761429008721),
+    (-0.608761429008721,  0.793353340291235),
+    (-0.130526192220052,  0.99144486137381),
+];
+
+// the 24 gradients repeated to fill one entry per hash index and pre-divided
+// by the normalizer, as in the reference, so lookup needs no modulo
+const GRADIENTS_2D: [(f64, f64); 1 << N_GRADS_2D_EXPONENT] = {
+    let mut table = [(0.0, 0.0); 1 << N_GRADS_2D_EXPONENT];
+    let mut i = 0;
+    while i < table.len() {
+        let (gx, gy) = BASE_GRADIENTS_2D[i % BASE_GRADIENTS_2D.len()];
+        table[i] = (gx / NORMALIZER_2D, gy / NORMALIZER_2D);
+        i += 1;
+    }
+    table
+};
+
+/// Evaluates OpenSimplex2 noise at a point; the result falls in roughly -1..1
+fn noise_2d(seed: i64, x: f64, y: f64) -> f32 {
+
+    // skew onto the simplex grid
+    let s = SKEW_2D * (x + y);
+    let xs = x + s;
+    let ys = y + s;
+
+    let xsb = xs.floor() as i64;
+    let ysb = ys.floor() as i64;
+    let xi = (xs - xsb as f64) as f32;
+    let yi = (ys - ysb as f64) as f32;
+
+    let xsbp = xsb.wrapping_mul(PRIME_X);
+    let ysbp = ysb.wrapping_mul(PRIME_Y);
+
+    // unskew back to get the offset from the base vertex
+    let t = (xi + yi) * UNSKEW_2D as f32;
+    let dx0 = xi + t;
+    let dy0 = yi + t;
+
+    let mut value = 0.0;
+
+    let a0 = R_SQUARED_2D - dx0 * dx0 - dy0 * dy0;
+    if a0 > 0.0 {
+        value += (a0 * a0) * (a0 * a0) * gradient(seed, xsbp, ysbp, dx0, dy0);
+    }
+
+    let a1 = (2.0 * (1.0 + 2.0 * UNSKEW_2D) * (1.0 / UNSKEW_2D + 2.0)) as f32 * t
+        + ((-2.0 * (1.0 + 2.0 * UNSKEW_2D) * (1.0 + 2.0 * UNSKEW_2D)) as f32 + a0);
+    if a1 > 0.0 {
+        let dx1 = dx0 - (1.0 + 2.0 * UNSKEW_2D) as f32;
+        let dy1 = dy0 - (1.0 + 2.0 * UNSKEW_2D) as f32;
+        value += (a1 * a1) * (a1 * a1) * gradient(seed, xsbp.wrapping_add(PRIME_X), ysbp.wrapping_add(PRIME_Y), dx1, dy1);
+    }
+
+    // the third vertex depends on which half of the rhombus we're in
+    let (dx2, dy2, xsvp, ysvp) = if dy0 > dx0 {
+        (dx0 - UNSKEW_2D as f32, dy0 - (UNSKEW_2D + 1.0) as f32, xsbp, ysbp.wrapping_add(PRIME_Y))
+    } else {
+        (dx0 - (UNSKEW_2D + 1.0) as f32, dy0 - UNSKEW_2D as f32, xsbp.wrapping_add(PRIME_X), ysbp)
+    };
+    let a2 = R_SQUARED_2D - dx2 * dx2 - dy2 * dy2;
+    if a2 > 0.0 {
+        value += (a2 * a2) * (a2 * a2) * gradient(seed, xsvp, ysvp, dx2, dy2);
+    }
+
+    value
+}
+
+fn gradient(seed: i64, xsvp: i64, ysvp: i64, dx: f32, dy: f32) -> f32 {
+    let (gx, gy) = GRADIENTS_2D[gradient_index(seed, xsvp, ysvp)];
+
+    (gx * dx as f64 + gy * dy as f64) as f32
+}
+
+fn gradient_index(seed: i64, xsvp: i64, ysvp: i64) -> usize {
+    let mut hash = (seed ^ xsvp ^ ysvp).wrapping_mul(HASH_MULTIPLIER);
+    hash ^= hash >> (64 - N_GRADS_2D_EXPONENT + 1);
+
+    (((hash as i32) & (((1 << N_GRADS_2D_EXPONENT) - 1) << 1)) >> 1) as usize
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::{gradient_index, noise_2d, BASE_GRADIENTS_2D, GRADIENTS_2D, NORMALIZER_2D, PRIME_X, PRIME_Y};
+
+    #[test]
+    fn test_range() {
+        let mut min = f32::INFINITY;
+        let mut max = f32::NEG_INFINITY;
+        for x in 0..200 {
+            for y in 0..200 {
+                let value = noise_2d(3, x as f64 * 0.037, y as f64 * 0.037);
+                min = min.min(value);
+                max = max.max(value);
+            }
+        }
+        assert!(min >= -1.0 && max <= 1.0);
+        assert!(min < -0.5 && max > 0.5);
+    }
+
+    #[test]
+    fn test_gradients_uniform() {
+        // every slot of the table should come up about as often as the others
+        let mut counts = [0; 128];
+        for x in 0..400i64 {
+            for y in 0..400i64 {
+                counts[gradient_index(11, x.wrapping_mul(PRIME_X), y.wrapping_mul(PRIME_Y))] += 1;
+            }
+        }
+
+        let expected = 400 * 400 / 128;
+        for &count in counts.iter() {
+            assert!((count - expected as i32).abs() < expected as i32 / 5, "{:?}", counts);
+        }
+    }
+
+    #[test]
+    fn test_gradient_table() {
+        for (i, &(gx, gy)) in GRADIENTS_2D.iter().enumerate() {
+            let (bx, by) = BASE_GRADIENTS_2D[i % BASE_GRADIENTS_2D.len()];
+            assert!((gx * NORMALIZER_2D - bx).abs() < 1e-12);
+            assert!((gy * NORMALIZER_2D - by).abs() < 1e-12);
+        }
+    }
+}
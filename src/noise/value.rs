@@ -0,0 +1,52 @@
+use crate::utils::interpolation::{lerp, smootherstep};
+use crate::utils::random::{hash_coords, hash_to_unit};
+use crate::utils::vec2::Vec2;
+use super::{NoiseFn, Seeded};
+
+/**
+ * Value noise: a random height at every lattice point, smoothly interpolated 
+ * in between. It's the point-evaluable cousin of diamond-square, which also 
+ * fills the space between random heights, and it's blobbier and more 
+ * grid-aligned than Perlin noise. Values are in -1..1.
+ */
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct ValueNoise {
+    pub seed: u64,
+}
+
+impl ValueNoise {
+
+    pub fn new(seed: u64) -> Self {
+        ValueNoise { seed }
+    }
+
+    fn height(&self, x: i64, y: i64) -> f32 {
+        hash_to_unit(hash_coords(self.seed, &[x, y])) * 2.0 - 1.0
+    }
+}
+
+impl NoiseFn for ValueNoise {
+
+    fn sample(&self, p: Vec2) -> f32 {
+        let x = p.x.floor() as i64;
+        let y = p.y.floor() as i64;
+        let u = smootherstep(0.0, 1.0, p.x - x as f32);
+        let v = smootherstep(0.0, 1.0, p.y - y as f32);
+
+        let top = lerp(self.height(x, y), self.height(x + 1, y), u);
+        let bottom = lerp(self.height(x, y + 1), self.height(x + 1, y + 1), u);
+
+        lerp(top, bottom, v)
+    }
+}
+
+impl Seeded for ValueNoise {
+
+    fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    fn with_seed(&self, seed: u64) -> Self {
+        ValueNoise { seed }
+    }
+}
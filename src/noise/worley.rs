@@ -0,0 +1,130 @@
+use serde::{Deserialize, Serialize};
+
+use crate::utils::random::{hash_coords, hash_to_unit};
+use crate::utils::vec2::Vec2;
+use super::{NoiseFn, Seeded};
+
+#[derive(Debug, Copy, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum DistanceMetric {
+    Euclidean,
+    Manhattan,
+    Chebyshev,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum WorleyChannel {
+    /// Distance to the nearest feature point
+    F1,
+    /// Distance to the second-nearest feature point
+    F2,
+    #[serde(rename = "f2-f1")]
+    F2MinusF1,
+    /// A random value shared by every pixel belonging to the same cell
+    CellId,
+}
+
+/**
+ * Worley (cellular) noise, with one feature point in each unit cell. Jitter is
+ * how far, from 0 to 1, each feature point may stray from the center of its
+ * cell. Distances are in units of cells, so they're 0 or more and rarely much
+ * above 1; cell IDs are in 0..1.
+ */
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Worley {
+    pub jitter: f32,
+    pub distance: DistanceMetric,
+    pub channel: WorleyChannel,
+    pub seed: u64,
+}
+
+impl NoiseFn for Worley {
+
+    fn sample(&self, p: Vec2) -> f32 {
+        sample_cells(self.seed, self.jitter, self.distance, p, None).value(self.channel)
+    }
+}
+
+impl Seeded for Worley {
+
+    fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    fn with_seed(&self, seed: u64) -> Self {
+        Worley { seed, ..*self }
+    }
+}
+
+pub(crate) struct WorleySample {
+    pub f1: f32,
+    pub f2: f32,
+    pub cell_id: f32,
+}
+
+impl WorleySample {
+
+    pub fn value(&self, channel: WorleyChannel) -> f32 {
+        match channel {
+            WorleyChannel::F1 => self.f1,
+            WorleyChannel::F2 => self.f2,
+            WorleyChannel::F2MinusF1 => self.f2 - self.f1,
+            WorleyChannel::CellId => self.cell_id,
+        }
+    }
+}
+
+/**
+ * Finds the nearest feature points to a position given in units of cells.
+ * When period is set, cell indices wrap around after that many cells in each
+ * direction, so the noise repeats.
+ */
+pub(crate) fn sample_cells(seed: u64, jitter: f32, metric: DistanceMetric, position: Vec2, period: Option<(usize, usize)>) -> WorleySample {
+    assert!((0.0..=1.0).contains(&jitter), "Jitter must be between 0 and 1");
+
+    let base_x = position.x.floor() as i64;
+    let base_y = position.y.floor() as i64;
+
+    let mut sample = WorleySample { f1: f32::INFINITY, f2: f32::INFINITY, cell_id: 0.0 };
+
+    // a heavily-jittered feature point can end up closer than its cell suggests,
+    // so look two cells out in each direction
+    for neighbor_x in (base_x - 2)..=(base_x + 2) {
+        for neighbor_y in (base_y - 2)..=(base_y + 2) {
+            let (hash_x, hash_y) = match period {
+                Some((cells_x, cells_y)) => (wrap(neighbor_x, cells_x), wrap(neighbor_y, cells_y)),
+                None => (neighbor_x, neighbor_y),
+            };
+
+            let offset_x = hash_to_unit(hash_coords(seed, &[hash_x, hash_y, 0])) - 0.5;
+            let offset_y = hash_to_unit(hash_coords(seed, &[hash_x, hash_y, 1])) - 0.5;
+            let point_x = neighbor_x as f32 + 0.5 + offset_x * jitter;
+            let point_y = neighbor_y as f32 + 0.5 + offset_y * jitter;
+
+            let distance = measure(metric, point_x - position.x, point_y - position.y);
+
+            if distance < sample.f1 {
+                sample.f2 = sample.f1;
+                sample.f1 = distance;
+                sample.cell_id = hash_to_unit(hash_coords(seed, &[hash_x, hash_y, 2]));
+            } else if distance < sample.f2 {
+                sample.f2 = distance;
+            }
+        }
+    }
+
+    sample
+}
+
+fn measure(metric: DistanceMetric, dx: f32, dy: f32) -> f32 {
+    match metric {
+        DistanceMetric::Euclidean => (dx * dx + dy * dy).sqrt(),
+        DistanceMetric::Manhattan => dx.abs() + dy.abs(),
+        DistanceMetric::Chebyshev => dx.abs().max(dy.abs()),
+    }
+}
+
+fn wrap(cell: i64, cells: usize) -> i64 {
+    cell.rem_euclid(cells as i64)
+}
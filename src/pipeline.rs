@@ -0,0 +1,195 @@
+use std::{fmt, fs, io, path::Path, str::FromStr};
+
+use serde::{Deserialize, Serialize};
+
+use crate::blend::{blend_image, BlendMode};
+use crate::generators::{
+    Generator, BricksParams, DiamondSquareParams, FbmParams, HillParams, PerlinParams, SimplexParams, WorleyParams
+};
+use crate::image::{GrayscaleColor, Image};
+
+/**
+ * A declarative recipe for an image: an ordered stack of generator layers, each
+ * blended onto the result of the ones before it. Pipelines are written in TOML:
+ * 
+ * ```toml
+ * width = 1024
+ * height = 1024
+ * 
+ * [[layers]]
+ * generator = "hill"
+ * location = [0.5, 0.5]
+ * shape = "smooth"
+ * radius = 0.5
+ * height = 1.0
+ * 
+ * [[layers]]
+ * generator = "perlin"
+ * grid_size = 13
+ * midpoint = 0.0
+ * scale = 0.1
+ * seed = 42
+ * blend = "add"
+ * opacity = 0.8
+ * ```
+ * 
+ * Each layer takes the fields of its generator's params struct, plus an 
+ * optional blend mode (default "normal") and opacity (default 1).
+ */
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Pipeline {
+    pub width: usize,
+    pub height: usize,
+    /// Value the image starts out filled with, before any layers
+    #[serde(default)]
+    pub background: GrayscaleColor,
+    pub layers: Vec<Layer>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Layer {
+    #[serde(flatten)]
+    pub generator: LayerGenerator,
+    #[serde(default = "default_blend")]
+    pub blend: BlendMode,
+    #[serde(default = "default_opacity")]
+    pub opacity: f32,
+}
+
+fn default_blend() -> BlendMode {
+    BlendMode::Normal
+}
+
+fn default_opacity() -> f32 {
+    1.0
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "generator", rename_all = "kebab-case")]
+pub enum LayerGenerator {
+    Bricks(BricksParams),
+    Hill(HillParams),
+    Perlin(PerlinParams),
+    DiamondSquare(DiamondSquareParams),
+    Fbm(FbmParams),
+    Simplex(SimplexParams),
+    Worley(WorleyParams),
+}
+
+impl LayerGenerator {
+
+    pub fn as_generator(&self) -> &dyn Generator {
+        match self {
+            LayerGenerator::Bricks(params) => params,
+            LayerGenerator::Hill(params) => params,
+            LayerGenerator::Perlin(params) => params,
+            LayerGenerator::DiamondSquare(params) => params,
+            LayerGenerator::Fbm(params) => params,
+            LayerGenerator::Simplex(params) => params,
+            LayerGenerator::Worley(params) => params,
+        }
+    }
+}
+
+impl Pipeline {
+
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, PipelineError> {
+        fs::read_to_string(path)?.parse()
+    }
+
+    pub fn render(&self) -> Image<GrayscaleColor> {
+        let mut image = Image::from_color(self.width, self.height, self.background);
+
+        for layer in self.layers.iter() {
+            let layer_image = layer.generator.as_generator().generate(self.width, self.height);
+            blend_image(&mut image, &layer_image, layer.blend, layer.opacity);
+        }
+
+        image
+    }
+}
+
+impl FromStr for Pipeline {
+    type Err = PipelineError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ok(toml::from_str(s)?)
+    }
+}
+
+#[derive(Debug)]
+pub enum PipelineError {
+    Io(io::Error),
+    Parse(toml::de::Error),
+}
+
+impl fmt::Display for PipelineError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PipelineError::Io(error) => write!(f, "couldn't read pipeline: {}", error),
+            PipelineError::Parse(error) => write!(f, "invalid pipeline: {}", error),
+        }
+    }
+}
+
+impl std::error::Error for PipelineError {}
+
+impl From<io::Error> for PipelineError {
+    fn from(error: io::Error) -> Self {
+        PipelineError::Io(error)
+    }
+}
+
+impl From<toml::de::Error> for PipelineError {
+    fn from(error: toml::de::Error) -> Self {
+        PipelineError::Parse(error)
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::{LayerGenerator, Pipeline};
+    use crate::blend::BlendMode;
+    use crate::generators::{HillShape, PerlinParams};
+
+    #[test]
+    fn test_checked_in_pipeline() {
+        let pipeline: Pipeline = include_str!("../pipelines/hill_with_noise.toml").parse().unwrap();
+
+        assert_eq!(pipeline.layers.len(), 2);
+        match &pipeline.layers[0].generator {
+            LayerGenerator::Hill(params) => assert_eq!(params.shape, HillShape::Smooth),
+            _ => panic!("expected a hill"),
+        }
+        assert_eq!(pipeline.layers[0].blend, BlendMode::Normal);
+        assert_eq!(pipeline.layers[1].blend, BlendMode::Add);
+    }
+
+    #[test]
+    fn test_render() {
+        let pipeline: Pipeline = "
+            width = 16
+            height = 8
+            background = 0.25
+
+            [[layers]]
+            generator = \"perlin\"
+            grid_size = 3
+            midpoint = 1
+            scale = 0
+            seed = 1
+            blend = \"add\"
+            opacity = 0.5
+        ".parse().unwrap();
+
+        assert_eq!(pipeline.layers[0].generator, LayerGenerator::Perlin(PerlinParams { grid_size: 3, midpoint: 1.0, scale: 0.0, seed: 1, period: None }));
+
+        let image = pipeline.render();
+        assert_eq!(image.width(), 16);
+        assert_eq!(image.height(), 8);
+        assert_eq!(*image.get(3, 3), 0.75);
+
+        assert!("width = 16".parse::<Pipeline>().is_err());
+    }
+}
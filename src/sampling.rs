@@ -0,0 +1,309 @@
+use std::f32::consts::PI;
+use std::ops::{Add, Mul};
+
+use serde::{Deserialize, Serialize};
+
+use crate::image::{EdgeMode, Image};
+
+/// How pixel values are reconstructed between pixel centers
+#[derive(Debug, Copy, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum SampleFilter {
+    /// The closest pixel; blocky, but never blends values
+    Nearest,
+    /// Linear blend of the 2x2 surrounding pixels
+    Bilinear,
+    /// Catmull-Rom spline through the 4x4 surrounding pixels; smoother than
+    /// bilinear, with slight overshoot at sharp edges
+    Bicubic,
+    /// Windowed sinc over the 6x6 surrounding pixels; the sharpest, and the
+    /// best choice for shrinking images
+    Lanczos,
+}
+
+impl SampleFilter {
+
+    /// How many pixels the kernel reaches out on each side of the sample
+    fn radius(&self) -> i64 {
+        match self {
+            SampleFilter::Nearest => 0,
+            SampleFilter::Bilinear => 1,
+            SampleFilter::Bicubic => 2,
+            SampleFilter::Lanczos => 3,
+        }
+    }
+
+    /// Weight of a pixel that's distance pixels away from the sample
+    fn weight(&self, distance: f32) -> f32 {
+        let distance = distance.abs();
+
+        match self {
+            SampleFilter::Nearest => if distance <= 0.5 { 1.0 } else { 0.0 },
+            SampleFilter::Bilinear => (1.0 - distance).max(0.0),
+            SampleFilter::Bicubic => {
+                if distance <= 1.0 {
+                    (1.5 * distance - 2.5) * distance * distance + 1.0
+                } else if distance < 2.0 {
+                    ((-0.5 * distance + 2.5) * distance - 4.0) * distance + 2.0
+                } else {
+                    0.0
+                }
+            },
+            SampleFilter::Lanczos => {
+                let radius = self.radius() as f32;
+                if distance < radius {
+                    sinc(distance) * sinc(distance / radius)
+                } else {
+                    0.0
+                }
+            },
+        }
+    }
+}
+
+fn sinc(x: f32) -> f32 {
+    if x == 0.0 {
+        1.0
+    } else {
+        (PI * x).sin() / (PI * x)
+    }
+}
+
+const MAX_TAPS: usize = 6;
+
+/// The pixels along one axis that contribute to a sample, and their weights
+struct Taps {
+    first: i64,
+    weights: [f32; MAX_TAPS],
+    count: usize,
+}
+
+fn taps(coord: f32, filter: SampleFilter) -> Taps {
+    let mut weights = [0.0; MAX_TAPS];
+
+    // every filter passes exactly through pixel centers, where all the other
+    // taps have zero weight; skipping them means sampling right on the last
+    // pixel doesn't read the one past the edge
+    if filter == SampleFilter::Nearest || coord.fract() == 0.0 {
+        weights[0] = 1.0;
+        return Taps { first: coord.round() as i64, weights, count: 1 };
+    }
+
+    let radius = filter.radius();
+    let first = coord.floor() as i64 - radius + 1;
+    let count = (radius * 2) as usize;
+
+    let mut total = 0.0;
+    for (i, weight) in weights.iter_mut().take(count).enumerate() {
+        *weight = filter.weight(coord - (first + i as i64) as f32);
+        total += *weight;
+    }
+
+    // Lanczos weights don't quite sum to 1 on their own
+    for weight in weights.iter_mut().take(count) {
+        *weight /= total;
+    }
+
+    Taps { first, weights, count }
+}
+
+impl<P: Copy + Add<Output=P> + Mul<Output=P> + Mul<f32, Output=P>> Image<P> {
+
+    /**
+     * Reads the image at a fractional position, reconstructing values between
+     * pixels with the given filter. Integer coordinates land exactly on pixel
+     * centers, so sample(x as f32, y as f32, ...) is the same as get(x, y).
+     * Pixels past the edges follow the image's edge mode.
+     */
+    pub fn sample(&self, x: f32, y: f32, filter: SampleFilter) -> P {
+        let taps_x = taps(x, filter);
+        let taps_y = taps(y, filter);
+
+        let mut sum: Option<P> = None;
+        for j in 0..taps_y.count {
+            for i in 0..taps_x.count {
+                let weight = taps_x.weights[i] * taps_y.weights[j];
+                let value = *self.get(taps_x.first + i as i64, taps_y.first + j as i64) * weight;
+
+                sum = Some(match sum {
+                    Some(sum) => sum + value,
+                    None => value,
+                });
+            }
+        }
+
+        sum.unwrap()
+    }
+
+    /**
+     * Resamples the image to a new size. When shrinking, the filter is 
+     * stretched to cover every source pixel that falls inside each new pixel, 
+     * so detail is averaged rather than skipped over. Pixels just past the 
+     * edges follow the image's edge mode, so Clamp usually gives the cleanest 
+     * borders unless the image is meant to tile. Resampling always reaches a
+     * little past the edges, so Panic is treated as Clamp here.
+     */
+    pub fn resize(&self, width: usize, height: usize, filter: SampleFilter) -> Image<P> {
+        assert!(width > 0 && height > 0, "Can't resize to an empty image");
+
+        // the filter is separable, so resize horizontally and then vertically
+        let columns = axis_weights(self.width(), width, filter);
+        let rows = axis_weights(self.height(), height, filter);
+
+        let horizontal = self.resize_axis(width, self.height(), &columns, (1, 0));
+        horizontal.resize_axis(width, height, &rows, (0, 1))
+    }
+
+    fn resize_axis(&self, width: usize, height: usize, weights: &[(i64, Vec<f32>)], direction: (i64, i64)) -> Image<P> {
+        let edge_mode = match self.edge_mode() {
+            EdgeMode::Panic => EdgeMode::Clamp,
+            edge_mode => edge_mode,
+        };
+
+        let mut pixels = Vec::with_capacity(width * height);
+        for y in 0..height as i64 {
+            for x in 0..width as i64 {
+                // the index along the axis being resized, and the pixel it starts from
+                let (index, start) = if direction.0 == 1 { (x, (0, y)) } else { (y, (x, 0)) };
+                let (first, ref pixel_weights) = weights[index as usize];
+
+                let mut sum: Option<P> = None;
+                for (i, weight) in pixel_weights.iter().enumerate() {
+                    let offset = first + i as i64;
+                    let value = *self.get_with_edge_mode(start.0 + offset * direction.0, start.1 + offset * direction.1, &edge_mode) * *weight;
+
+                    sum = Some(match sum {
+                        Some(sum) => sum + value,
+                        None => value,
+                    });
+                }
+
+                pixels.push(sum.unwrap());
+            }
+        }
+
+        Image::from_pixels(width, height, pixels, self.edge_mode())
+    }
+}
+
+/// For each pixel along a resized axis, the first source pixel it reads and 
+/// the weights of it and the following source pixels
+fn axis_weights(source_size: usize, target_size: usize, filter: SampleFilter) -> Vec<(i64, Vec<f32>)> {
+    let scale = source_size as f32 / target_size as f32;
+    let stretch = scale.max(1.0);
+
+    (0..target_size).map(|i| {
+        let center = (i as f32 + 0.5) * scale - 0.5;
+
+        if filter == SampleFilter::Nearest {
+            return ((center + 0.5).floor() as i64, vec![1.0]);
+        }
+
+        let reach = filter.radius() as f32 * stretch;
+        let first = (center - reach).ceil() as i64;
+        let last = (center + reach).floor() as i64;
+
+        let mut weights: Vec<f32> = (first..=last)
+            .map(|source| filter.weight((source as f32 - center) / stretch))
+            .collect();
+        let total: f32 = weights.iter().sum();
+        for weight in weights.iter_mut() {
+            *weight /= total;
+        }
+
+        (first, weights)
+    }).collect()
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::SampleFilter;
+    use crate::image::{EdgeMode, Image};
+
+    const FILTERS: [SampleFilter; 4] = [SampleFilter::Nearest, SampleFilter::Bilinear, SampleFilter::Bicubic, SampleFilter::Lanczos];
+
+    fn gradient() -> Image<f32> {
+        let mut image = Image::from_color(8, 8, 0.0);
+        for x in 0..8 {
+            for y in 0..8 {
+                image.set(x, y, ((x * 3 + y * 5) % 7) as f32);
+            }
+        }
+        image
+    }
+
+    #[test]
+    fn test_pixel_centers() {
+        let image = gradient();
+
+        for filter in FILTERS.iter() {
+            for x in 0..8 {
+                for y in 0..8 {
+                    let sampled = image.sample(x as f32, y as f32, *filter);
+                    assert!((sampled - *image.get(x, y)).abs() < 0.0001, "{:?} at ({}, {})", filter, x, y);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_resize() {
+        let image = gradient();
+
+        for filter in FILTERS.iter() {
+            let same = image.resize(8, 8, *filter);
+            assert!((same.sample(3.0, 5.0, SampleFilter::Nearest) - *image.get(3, 5)).abs() < 0.0001, "{:?}", filter);
+
+            let resized = Image::from_color(10, 6, 0.25).resize(7, 13, *filter);
+            assert_eq!((resized.width(), resized.height()), (7, 13));
+            assert!((*resized.get(4, 9) - 0.25).abs() < 0.0001, "{:?}", filter);
+        }
+
+        // shrinking a checkerboard by half averages each 2x2 block
+        let mut checkers = Image::from_color(4, 4, 0.0);
+        for x in 0..4 {
+            for y in 0..4 {
+                checkers.set(x, y, ((x + y) % 2) as f32);
+            }
+        }
+        let shrunk = checkers.resize(2, 2, SampleFilter::Bilinear);
+        assert!((*shrunk.get(0, 0) - 0.5).abs() < 0.0001);
+    }
+
+    #[test]
+    fn test_resize_panic_edges() {
+        let image = gradient().with_edge_mode(EdgeMode::Panic);
+
+        for filter in FILTERS.iter() {
+            let resized = image.resize(13, 20, *filter);
+            assert_eq!((resized.width(), resized.height()), (13, 20));
+            assert_eq!(resized.edge_mode(), EdgeMode::Panic);
+        }
+    }
+
+    #[test]
+    fn test_between_pixels() {
+        let mut image = Image::from_color(2, 1, 0.0).with_edge_mode(EdgeMode::Clamp);
+        image.set(1, 0, 1.0);
+
+        assert_eq!(image.sample(0.25, 0.0, SampleFilter::Bilinear), 0.25);
+        assert_eq!(image.sample(0.4, 0.0, SampleFilter::Nearest), 0.0);
+        assert_eq!(image.sample(0.6, 0.0, SampleFilter::Nearest), 1.0);
+        assert_eq!(image.sample(1.5, 0.0, SampleFilter::Bilinear), 1.0);
+        assert!((image.sample(0.5, 0.0, SampleFilter::Bicubic) - 0.5).abs() < 0.0001);
+    }
+
+    #[test]
+    fn test_last_pixel_stays_inside() {
+        let image = gradient().with_edge_mode(EdgeMode::Panic);
+
+        for filter in FILTERS.iter() {
+            assert_eq!(image.sample(7.0, 7.0, *filter), *image.get(7, 7), "{:?}", filter);
+            assert_eq!(image.sample(0.0, 7.0, *filter), *image.get(0, 7), "{:?}", filter);
+        }
+
+        // only the last row is read, since the sample is right on it
+        assert_eq!(image.sample(6.5, 7.0, SampleFilter::Bilinear), (*image.get(6, 7) + *image.get(7, 7)) / 2.0);
+    }
+}
@@ -0,0 +1,55 @@
+use std::f32::consts::PI;
+
+/// Linear interpolation from a (t = 0) to b (t = 1)
+pub fn lerp(a: f32, b: f32, t: f32) -> f32 {
+    (b - a) * t + a
+}
+
+/// Interpolation from a to b that eases in and out along a sine curve
+pub fn serp(a: f32, b: f32, t: f32) -> f32 {
+    lerp(a, b, ((t - 0.5) * PI).sin() / 2.0 + 0.5)
+}
+
+/**
+ * 0 below a, 1 above b, and a smooth S-curve in between. With a = 0 and b = 1 
+ * it turns a linear 0..1 blend factor into one that eases in and out.
+ */
+pub fn smoothstep(a: f32, b: f32, t: f32) -> f32 {
+
+    // Scale, bias and saturate x to 0..1 range
+    let range = b - a;
+    let portion = (t - a) / range;
+    let portion = clamp(portion, 0.0, 1.0);
+
+    // Evaluate polynomial
+    portion * portion * (3.0 - 2.0 * portion)
+}
+
+/// Like smoothstep, but its slope also eases in and out, so there's no visible
+/// crease where one interpolated cell meets the next
+pub fn smootherstep(a: f32, b: f32, t: f32) -> f32 {
+    let portion = clamp((t - a) / (b - a), 0.0, 1.0);
+    portion * portion * portion * (portion * (portion * 6.0 - 15.0) + 10.0)
+}
+
+pub fn clamp(x: f32, min: f32, max: f32) -> f32 {
+    x.max(min).min(max)
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::{lerp, serp, smoothstep, smootherstep};
+
+    #[test]
+    fn test_endpoints() {
+        assert_eq!(lerp(2.0, 4.0, 0.5), 3.0);
+        assert!((serp(2.0, 4.0, 0.0) - 2.0).abs() < 0.0001);
+        assert!((serp(2.0, 4.0, 1.0) - 4.0).abs() < 0.0001);
+        assert_eq!(smoothstep(0.0, 1.0, -1.0), 0.0);
+        assert_eq!(smoothstep(0.0, 1.0, 0.5), 0.5);
+        assert_eq!(smoothstep(0.0, 1.0, 2.0), 1.0);
+        assert_eq!(smootherstep(0.0, 1.0, 0.5), 0.5);
+        assert_eq!(smootherstep(0.0, 1.0, 1.0), 1.0);
+    }
+}
@@ -1,4 +1,5 @@
-
 pub mod vec2;
 pub mod vec3;
-pub mod matrix;
\ No newline at end of file
+pub mod matrix;
+pub mod random;
+pub mod interpolation;
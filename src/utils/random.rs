@@ -0,0 +1,40 @@
+extern crate rand;
+extern crate rand_chacha;
+
+use rand::SeedableRng;
+use rand_chacha::ChaCha8Rng;
+
+/// The RNG every generator draws from. ChaCha is used (instead of `StdRng`)
+/// because its output is guaranteed not to change between rand versions, so a
+/// stored seed will keep producing the same image.
+pub type SeededRng = ChaCha8Rng;
+
+pub fn seeded_rng(seed: u64) -> SeededRng {
+    SeededRng::seed_from_u64(seed)
+}
+
+/**
+ * Deterministically hashes a set of integer coordinates together with a seed. 
+ * Used by generators that need the same "random" value every time a given
+ * point is visited, no matter what order points are visited in.
+ */
+pub fn hash_coords(seed: u64, coords: &[i64]) -> u64 {
+    let mut hash = mix(seed);
+    for &coord in coords {
+        hash = mix(hash ^ coord as u64);
+    }
+    hash
+}
+
+/// Maps a hash to a float in 0..1
+pub fn hash_to_unit(hash: u64) -> f32 {
+    (hash >> 40) as f32 / (1u64 << 24) as f32
+}
+
+// splitmix64 finalizer
+fn mix(x: u64) -> u64 {
+    let mut z = x.wrapping_add(0x9E3779B97F4A7C15);
+    z = (z ^ (z >> 30)).wrapping_mul(0xBF58476D1CE4E5B9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94D049BB133111EB);
+    z ^ (z >> 31)
+}